@@ -0,0 +1,220 @@
+use std::io::{self, BufRead, Read, Write};
+use std::mem::size_of;
+
+use crate::container::{LineEnding, Record, RecordReader};
+use crate::errors::ContainerError;
+
+pub const BATCH_BYTES: usize = 8 << 20;
+
+// Splits input into lines like `BufRead::lines`, remembering how they ended so
+// unpacking can write back the same bytes. Input mixing LF and CRLF endings is
+// rejected rather than silently normalized.
+pub struct LineReader<R: BufRead> {
+    inner: R,
+    line_ending: Option<LineEnding>,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, line_ending: None }
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending.unwrap_or_default()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    fn strip_line_ending(&mut self, mut line: String) -> io::Result<String> {
+        if !line.ends_with('\n') {
+            return Ok(line);
+        }
+        line.pop();
+        let line_ending = match line.ends_with('\r') {
+            true => {
+                line.pop();
+                LineEnding::CrLf
+            }
+            false => LineEnding::Lf,
+        };
+        if *self.line_ending.get_or_insert(line_ending) != line_ending {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Input mixes LF and CRLF line endings"));
+        }
+        Ok(line)
+    }
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.inner.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(self.strip_line_ending(line)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+// Writes unpacked lines back with the original line ending. Each ending is only
+// written once the next line arrives, so `finish_lines` decides whether the
+// content ends with one.
+pub struct LineWriter<W: Write> {
+    inner: W,
+    line_ending: LineEnding,
+    pending: bool,
+    written: u64,
+}
+
+impl<W: Write> LineWriter<W> {
+    pub fn new(inner: W, line_ending: LineEnding) -> Self {
+        Self { inner, line_ending, pending: false, written: 0 }
+    }
+
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        self.end_line()?;
+        self.inner.write_all(line)?;
+        self.written += line.len() as u64;
+        self.pending = true;
+        Ok(())
+    }
+
+    pub fn finish_lines(&mut self, final_newline: bool) -> io::Result<()> {
+        match final_newline {
+            true => self.end_line(),
+            false => {
+                self.pending = false;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.written
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        if self.pending {
+            self.inner.write_all(self.line_ending.as_bytes())?;
+            self.written += self.line_ending.as_bytes().len() as u64;
+            self.pending = false;
+        }
+        Ok(())
+    }
+}
+
+// Reads lines until roughly `max_bytes` are buffered, always stopping on a
+// multiple of `group` lines so multi-line records are never split.
+pub fn read_line_batch<I: Iterator<Item = io::Result<String>>>(lines: &mut I, max_bytes: usize, group: usize) -> io::Result<Vec<String>> {
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0;
+    while batch_bytes < max_bytes || !batch.len().is_multiple_of(group) {
+        match lines.next() {
+            Some(line) => {
+                let line = line?;
+                batch_bytes += line.len() + size_of::<String>();
+                batch.push(line);
+            }
+            None => break,
+        }
+    }
+    Ok(batch)
+}
+
+// Like `read_line_batch`, batches hold a multiple of `group` records. Index
+// and footer records describe the archive itself and are skipped.
+pub fn read_record_batch<R: Read>(reader: &mut RecordReader<R>, max_bytes: usize, group: usize) -> Result<Vec<Record>, ContainerError> {
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0;
+    while batch_bytes < max_bytes || !batch.len().is_multiple_of(group) {
+        match reader.next_record()? {
+            Some(record) if record.kind.is_metadata() => continue,
+            Some(record) => {
+                batch_bytes += record.payload.len() + size_of::<Record>();
+                batch.push(record);
+            }
+            None => break,
+        }
+    }
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::{Footer, RecordKind};
+
+    #[test]
+    fn test_read_line_batch() {
+        let input = "AAAA\nCCCC\nGGGG\nTTTT\nNNNN\n";
+        let mut lines = input.as_bytes().lines();
+        let line_cost = 4 + size_of::<String>();
+
+        assert_eq!(read_line_batch(&mut lines, 2 * line_cost, 1).unwrap(), vec!["AAAA", "CCCC"]);
+        assert_eq!(read_line_batch(&mut lines, 1, 2).unwrap(), vec!["GGGG", "TTTT"]);
+        assert_eq!(read_line_batch(&mut lines, BATCH_BYTES, 1).unwrap(), vec!["NNNN"]);
+        assert!(read_line_batch(&mut lines, BATCH_BYTES, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_line_reader() {
+        let mut lines = LineReader::new("AAAA\r\nCCCC\r\n\r\nGGGG".as_bytes());
+        assert_eq!(read_line_batch(&mut lines, BATCH_BYTES, 1).unwrap(), vec!["AAAA", "CCCC", "", "GGGG"]);
+        assert_eq!(lines.line_ending(), LineEnding::CrLf);
+
+        let mut lines = LineReader::new("AAAA\nCCCC\n".as_bytes());
+        assert_eq!(read_line_batch(&mut lines, BATCH_BYTES, 1).unwrap(), vec!["AAAA", "CCCC"]);
+        assert_eq!(lines.line_ending(), LineEnding::Lf);
+
+        let mut lines = LineReader::new("AAAA\nCCCC\r\n".as_bytes());
+        let err = read_line_batch(&mut lines, BATCH_BYTES, 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_line_writer() {
+        let mut writer = LineWriter::new(Vec::new(), LineEnding::CrLf);
+        writer.write_line(b"AAAA").unwrap();
+        writer.write_line(b"").unwrap();
+        writer.write_line(b"CC").unwrap();
+        assert_eq!(writer.bytes_written(), 10);
+        writer.finish_lines(true).unwrap();
+        assert_eq!(writer.bytes_written(), 12);
+        assert_eq!(writer.into_inner(), b"AAAA\r\n\r\nCC\r\n");
+
+        let mut writer = LineWriter::new(Vec::new(), LineEnding::Lf);
+        writer.write_line(b"AAAA").unwrap();
+        writer.write_line(b"CC").unwrap();
+        writer.finish_lines(false).unwrap();
+        assert_eq!(writer.into_inner(), b"AAAA\nCC");
+    }
+
+    #[test]
+    fn test_read_record_batch() {
+        let mut bytes = Vec::new();
+        for i in 0..5u8 {
+            Record::new(RecordKind::Sequence, vec![i; 10]).write_to(&mut bytes).unwrap();
+        }
+        Record::new(RecordKind::Index, vec![0]).write_to(&mut bytes).unwrap();
+        Footer { record_count: 6, ..Footer::default() }.to_record().write_to(&mut bytes).unwrap();
+        let mut reader = RecordReader::new(bytes.as_slice());
+
+        assert_eq!(read_record_batch(&mut reader, 1, 1).unwrap().len(), 1);
+        assert_eq!(read_record_batch(&mut reader, 1, 2).unwrap().len(), 2);
+        let rest = read_record_batch(&mut reader, BATCH_BYTES, 1).unwrap();
+        assert_eq!(rest.iter().map(|record| record.payload[0]).collect::<Vec<_>>(), vec![3, 4]);
+        assert!(read_record_batch(&mut reader, BATCH_BYTES, 1).unwrap().is_empty());
+        assert_eq!(reader.footer().unwrap().record_count, 6);
+    }
+}
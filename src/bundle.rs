@@ -0,0 +1,316 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path};
+
+use crate::checksum::ChecksumWriter;
+use crate::container::{Header, SourceFormat};
+use crate::errors::BundleError;
+use crate::mode::Mode;
+use crate::stdio::{create_output, is_stdio};
+use crate::unpack_reader_to_writer;
+use crate::varint::{read_varint, write_varint};
+
+// A whole directory in one file: the compressed archive of every member back
+// to back, then a table of contents and a fixed size trailer holding the
+// offset and CRC32 of the table, so members can be listed and read without
+// scanning the bundle.
+pub const BUNDLE_MAGIC: &[u8; 4] = b"CSQD";
+pub const BUNDLE_VERSION: u8 = 1;
+const PREFIX_LEN: u64 = 5;
+const TRAILER_LEN: u64 = 16;
+
+// Paths are relative to the bundled directory and always `/` separated. The
+// mode is the sequence mode the member was compressed with, the permissions
+// are those of the original file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleEntry {
+    pub path: String,
+    pub mode: Mode,
+    pub permissions: u32,
+    pub original_len: u64,
+    pub compressed_len: u64,
+    pub offset: u64,
+    pub checksum: u32,
+}
+
+impl BundleEntry {
+    fn write_to(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.path.len() as u64);
+        out.extend_from_slice(self.path.as_bytes());
+        out.push(self.mode.id());
+        write_varint(out, self.permissions as u64);
+        write_varint(out, self.original_len);
+        write_varint(out, self.compressed_len);
+        write_varint(out, self.offset);
+        out.extend_from_slice(&self.checksum.to_le_bytes());
+    }
+
+    fn read_from(input: &mut &[u8]) -> Result<Self, BundleError> {
+        let read_value = |input: &mut &[u8]| read_varint(input).ok_or(BundleError::CorruptToc);
+        let path_len = read_value(input)? as usize;
+        if input.len() < path_len {
+            return Err(BundleError::CorruptToc);
+        }
+        let (path, rest) = input.split_at(path_len);
+        *input = rest;
+        let path = String::from_utf8(path.to_vec()).map_err(|_| BundleError::CorruptToc)?;
+        if !is_safe_member_path(&path) {
+            return Err(BundleError::InvalidMemberPath(path));
+        }
+        let (&mode_id, rest) = input.split_first().ok_or(BundleError::CorruptToc)?;
+        *input = rest;
+        let mode = Mode::from_id(mode_id).ok_or(BundleError::CorruptToc)?;
+        let permissions = u32::try_from(read_value(input)?).map_err(|_| BundleError::CorruptToc)?;
+        let original_len = read_value(input)?;
+        let compressed_len = read_value(input)?;
+        let offset = read_value(input)?;
+        if input.len() < 4 {
+            return Err(BundleError::CorruptToc);
+        }
+        let (checksum, rest) = input.split_at(4);
+        *input = rest;
+        let checksum = u32::from_le_bytes(checksum.try_into().unwrap());
+        Ok(Self { path, mode, permissions, original_len, compressed_len, offset, checksum })
+    }
+}
+
+// Members are unpacked below an output directory, so their paths must not
+// escape it.
+fn is_safe_member_path(path: &str) -> bool {
+    !path.is_empty() && Path::new(path).components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+pub struct BundleWriter<W: Write> {
+    inner: W,
+    offset: u64,
+    entries: Vec<BundleEntry>,
+}
+
+impl<W: Write> BundleWriter<W> {
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(BUNDLE_MAGIC)?;
+        inner.write_all(&[BUNDLE_VERSION])?;
+        Ok(Self { inner, offset: PREFIX_LEN, entries: Vec::new() })
+    }
+
+    pub fn add_member<R: Read>(&mut self, path: &str, mode: &Mode, permissions: u32, original_len: u64, member: &mut R) -> Result<(), BundleError> {
+        if !is_safe_member_path(path) {
+            return Err(BundleError::InvalidMemberPath(path.to_string()));
+        }
+        let mut writer = ChecksumWriter::new(&mut self.inner);
+        let compressed_len = io::copy(member, &mut writer)?;
+        let checksum = writer.checksum();
+        self.entries.push(BundleEntry { path: path.to_string(), mode: mode.clone(), permissions, original_len, compressed_len, offset: self.offset, checksum });
+        self.offset += compressed_len;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut toc = Vec::new();
+        write_varint(&mut toc, self.entries.len() as u64);
+        for entry in &self.entries {
+            entry.write_to(&mut toc);
+        }
+        self.inner.write_all(&toc)?;
+        self.inner.write_all(&self.offset.to_le_bytes())?;
+        self.inner.write_all(&crc32fast::hash(&toc).to_le_bytes())?;
+        self.inner.write_all(BUNDLE_MAGIC)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+// Stdin cannot be peeked at, so it is never taken for a bundle.
+pub fn is_bundle(path: &str) -> bool {
+    let mut magic = [0u8; 4];
+    !is_stdio(path) && File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == BUNDLE_MAGIC
+}
+
+pub struct Bundle {
+    path: String,
+    entries: Vec<BundleEntry>,
+}
+
+impl Bundle {
+    pub fn open(path: &str) -> Result<Self, BundleError> {
+        let mut file = File::open(path)?;
+        let mut prefix = [0u8; PREFIX_LEN as usize];
+        if file.read_exact(&mut prefix).is_err() || &prefix[..4] != BUNDLE_MAGIC {
+            return Err(BundleError::NotABundle);
+        }
+        if prefix[4] != BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion(prefix[4]));
+        }
+
+        let len = file.metadata()?.len();
+        if len < PREFIX_LEN + TRAILER_LEN {
+            return Err(BundleError::CorruptToc);
+        }
+        let toc_end = len - TRAILER_LEN;
+        let mut trailer = [0u8; TRAILER_LEN as usize];
+        file.seek(SeekFrom::Start(toc_end))?;
+        file.read_exact(&mut trailer)?;
+        let toc_offset = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        let checksum = u32::from_le_bytes(trailer[8..12].try_into().unwrap());
+        if &trailer[12..] != BUNDLE_MAGIC || toc_offset < PREFIX_LEN || toc_offset > toc_end {
+            return Err(BundleError::CorruptToc);
+        }
+
+        let mut toc = vec![0u8; (toc_end - toc_offset) as usize];
+        file.seek(SeekFrom::Start(toc_offset))?;
+        file.read_exact(&mut toc)?;
+        if crc32fast::hash(&toc) != checksum {
+            return Err(BundleError::CorruptToc);
+        }
+        let mut input = toc.as_slice();
+        let entry_count = read_varint(&mut input).ok_or(BundleError::CorruptToc)?;
+        let mut entries = Vec::new();
+        for _ in 0..entry_count {
+            let entry = BundleEntry::read_from(&mut input)?;
+            if entry.offset < PREFIX_LEN || entry.offset.saturating_add(entry.compressed_len) > toc_offset {
+                return Err(BundleError::CorruptToc);
+            }
+            entries.push(entry);
+        }
+        Ok(Self { path: path.to_string(), entries })
+    }
+
+    pub fn entries(&self) -> &[BundleEntry] {
+        &self.entries
+    }
+
+    pub fn find(&self, path: &str) -> Result<&BundleEntry, BundleError> {
+        self.entries.iter().find(|entry| entry.path == path).ok_or_else(|| BundleError::UnknownMember(path.to_string()))
+    }
+
+    // Each reader has its own file handle, so members can be read in parallel.
+    pub fn member_reader(&self, entry: &BundleEntry) -> io::Result<io::Take<BufReader<File>>> {
+        let mut file = BufReader::new(File::open(&self.path)?);
+        file.seek(SeekFrom::Start(entry.offset))?;
+        Ok(file.take(entry.compressed_len))
+    }
+
+    // Writes the member's compressed archive as it is stored.
+    pub fn copy_member<W: Write>(&self, entry: &BundleEntry, writer: W) -> Result<W, BundleError> {
+        let mut writer = ChecksumWriter::new(writer);
+        io::copy(&mut self.member_reader(entry)?, &mut writer)?;
+        if writer.checksum() != entry.checksum {
+            return Err(BundleError::MemberChecksumMismatch(entry.path.clone()));
+        }
+        Ok(writer.into_inner())
+    }
+
+    pub fn member_format(&self, entry: &BundleEntry) -> Result<SourceFormat, Box<dyn Error>> {
+        Ok(Header::read_from(&mut self.member_reader(entry)?)?.format)
+    }
+
+    // Unpacks a member to its original content and restores its permissions.
+    // A line width only applies to FASTA members.
+    pub fn unpack_member(&self, entry: &BundleEntry, output: &str, mode: Option<&Mode>, line_width: Option<usize>) -> Result<(), Box<dyn Error>> {
+        let format = self.member_format(entry)?;
+        let line_width = line_width.filter(|_| format == SourceFormat::Fasta);
+        let reader = Box::new(self.member_reader(entry)?);
+        unpack_reader_to_writer(&entry.path, reader, format, mode, line_width, create_output(output)?)?.flush()?;
+        if !is_stdio(output) {
+            set_file_mode(Path::new(output), entry.permissions)?;
+        }
+        Ok(())
+    }
+
+    // Checks the stored member checksum and decodes it without writing anything.
+    pub fn verify_member(&self, entry: &BundleEntry, mode: Option<&Mode>) -> Result<(), Box<dyn Error>> {
+        self.copy_member(entry, io::sink())?;
+        let format = self.member_format(entry)?;
+        unpack_reader_to_writer(&entry.path, Box::new(self.member_reader(entry)?), format, mode, None, io::sink())?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+pub fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+pub fn file_mode(metadata: &fs::Metadata) -> u32 {
+    match metadata.permissions().readonly() {
+        true => 0o444,
+        false => 0o644,
+    }
+}
+
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(path: &Path, mode: u32) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o200 == 0);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_bundle(path: &str, members: &[(&str, &[u8])]) {
+        let mut writer = BundleWriter::new(File::create(path).unwrap()).unwrap();
+        for (name, member) in members {
+            writer.add_member(name, &Mode::RNA, 0o640, 100, &mut &member[..]).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let path = "test_bundle_round_trip.cseqd";
+        write_bundle(path, &[("a.txt", b"first"), ("sub/b.fa", b"second member")]);
+        assert!(is_bundle(path));
+
+        let bundle = Bundle::open(path).unwrap();
+        let paths: Vec<&str> = bundle.entries().iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt", "sub/b.fa"]);
+        let entry = bundle.find("sub/b.fa").unwrap();
+        assert_eq!((&entry.mode, entry.permissions, entry.original_len, entry.compressed_len, entry.offset), (&Mode::RNA, 0o640, 100, 13, 10));
+        assert_eq!(bundle.copy_member(entry, Vec::new()).unwrap(), b"second member");
+        assert!(matches!(bundle.find("b.fa"), Err(BundleError::UnknownMember(_))));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_bundle_detects_corruption() {
+        let path = "test_bundle_corruption.cseqd";
+        write_bundle(path, &[("a.txt", b"first")]);
+        let original = std::fs::read(path).unwrap();
+
+        let mut bytes = original.clone();
+        bytes[6] ^= 1;
+        std::fs::write(path, &bytes).unwrap();
+        let bundle = Bundle::open(path).unwrap();
+        let err = bundle.copy_member(&bundle.entries()[0], Vec::new()).unwrap_err();
+        assert!(matches!(err, BundleError::MemberChecksumMismatch(_)));
+
+        let mut bytes = original.clone();
+        bytes[11] ^= 1;
+        std::fs::write(path, &bytes).unwrap();
+        assert!(matches!(Bundle::open(path), Err(BundleError::CorruptToc)));
+
+        std::fs::write(path, &original[..original.len() - 1]).unwrap();
+        assert!(matches!(Bundle::open(path), Err(BundleError::CorruptToc)));
+        std::fs::write(path, b"CSEQ").unwrap();
+        assert!(!is_bundle(path));
+        assert!(matches!(Bundle::open(path), Err(BundleError::NotABundle)));
+
+        let mut writer = BundleWriter::new(Vec::new()).unwrap();
+        assert!(matches!(writer.add_member("../escape.txt", &Mode::DNA, 0o644, 0, &mut &b""[..]), Err(BundleError::InvalidMemberPath(_))));
+        assert!(matches!(writer.add_member("/etc/passwd", &Mode::DNA, 0o644, 0, &mut &b""[..]), Err(BundleError::InvalidMemberPath(_))));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+use crate::errors::CompressionError;
+use crate::varint::{read_varint, write_varint};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaseMask {
+    intervals: Vec<(usize, usize)>,
+}
+
+impl CaseMask {
+    pub fn from_sequence(sequence: &[u8]) -> Self {
+        let mut intervals: Vec<(usize, usize)> = Vec::new();
+        for (i, base) in sequence.iter().enumerate() {
+            if !base.is_ascii_lowercase() {
+                continue;
+            }
+            match intervals.last_mut() {
+                Some((start, len)) if *start + *len == i => *len += 1,
+                _ => intervals.push((i, 1)),
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn apply(&self, sequence: &mut [u8]) -> Result<(), CompressionError> {
+        for &(start, len) in &self.intervals {
+            let interval = sequence
+                .get_mut(start..start.saturating_add(len))
+                .ok_or_else(|| CompressionError::InvalidEncoding("case mask outside of the sequence".to_string()))?;
+            interval.make_ascii_lowercase();
+        }
+        Ok(())
+    }
+
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.intervals.len() as u64);
+        let mut previous_end = 0;
+        for &(start, len) in &self.intervals {
+            write_varint(out, (start - previous_end) as u64);
+            write_varint(out, len as u64);
+            previous_end = start + len;
+        }
+    }
+
+    pub fn read_from(input: &mut &[u8]) -> Result<Self, CompressionError> {
+        let count = read_length(input)?;
+        let mut intervals = Vec::with_capacity(count.min(input.len()));
+        let mut previous_end = 0usize;
+        for _ in 0..count {
+            let start = previous_end.saturating_add(read_length(input)?);
+            let len = read_length(input)?;
+            previous_end = start.saturating_add(len);
+            intervals.push((start, len));
+        }
+        Ok(Self { intervals })
+    }
+}
+
+fn read_length(input: &mut &[u8]) -> Result<usize, CompressionError> {
+    read_varint(input)
+        .map(|value| value as usize)
+        .ok_or_else(|| CompressionError::InvalidEncoding("truncated case mask".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sequence() {
+        assert!(CaseMask::from_sequence(b"ACGTNNRY").is_empty());
+
+        let mask = CaseMask::from_sequence(b"acGTnnnnACgt");
+        assert_eq!(mask.intervals, vec![(0, 2), (4, 4), (10, 2)]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sequence = b"ACGTacgtnnnnACGTNNNNaAcC";
+        let mask = CaseMask::from_sequence(sequence);
+
+        let mut bytes = Vec::new();
+        mask.write_to(&mut bytes);
+        let mut input = bytes.as_slice();
+        let decoded_mask = CaseMask::read_from(&mut input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(decoded_mask, mask);
+
+        let mut restored = sequence.to_ascii_uppercase();
+        decoded_mask.apply(&mut restored).unwrap();
+        assert_eq!(restored, sequence);
+    }
+
+    #[test]
+    fn test_invalid_mask() {
+        let mask = CaseMask::from_sequence(b"ACGTac");
+        let mut short = b"ACG".to_vec();
+        assert!(matches!(mask.apply(&mut short), Err(CompressionError::InvalidEncoding(_))));
+
+        let mut input: &[u8] = &[2, 0];
+        assert!(matches!(CaseMask::read_from(&mut input), Err(CompressionError::InvalidEncoding(_))));
+    }
+}
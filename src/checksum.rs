@@ -0,0 +1,52 @@
+use std::io::{self, Write};
+
+// Passes writes through while keeping a running CRC32 of everything written,
+// so unpacked output can be checked against the footer without buffering it.
+pub struct ChecksumWriter<W: Write> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, hasher: crc32fast::Hasher::new() }
+    }
+
+    pub fn checksum(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_matches_written_bytes() {
+        let mut writer = ChecksumWriter::new(Vec::new());
+        writer.write_all(b">seq1\nACGT").unwrap();
+        writer.write_all(b"ACGT\n").unwrap();
+
+        let checksum = writer.checksum();
+        let written = writer.into_inner();
+        assert_eq!(written, b">seq1\nACGTACGT\n");
+        assert_eq!(checksum, crc32fast::hash(&written));
+        assert_ne!(checksum, crc32fast::hash(b">seq1\nACGTACGA\n"));
+    }
+}
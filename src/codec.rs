@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::encoders::{AsciiCodec, ProteinCodec, TwoBitCodec};
+use crate::errors::CompressionError;
+use crate::mode::Mode;
+
+pub const DEFAULT_CODEC: &str = "ascii";
+pub const DEFAULT_PROTEIN_CODEC: &str = "protein";
+
+pub trait Codec: Send + Sync {
+    fn id(&self) -> &str;
+    fn encode(&self, sequence: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError>;
+    fn decode(&self, encoded: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError>;
+
+    // Codecs whose output for a sequence is the concatenation of the outputs for
+    // its pieces, when split at multiples of this size, get long lines encoded in parallel.
+    fn chunk_alignment(&self) -> Option<usize> {
+        None
+    }
+}
+
+type CodecFactory = Box<dyn Fn(&Mode) -> Result<Box<dyn Codec>, CompressionError> + Send + Sync>;
+
+lazy_static::lazy_static! {
+    static ref CODEC_REGISTRY: RwLock<HashMap<String, CodecFactory>> = RwLock::new(builtin_codecs());
+}
+
+fn builtin_codecs() -> HashMap<String, CodecFactory> {
+    let mut codecs: HashMap<String, CodecFactory> = HashMap::new();
+    codecs.insert("ascii".to_string(), Box::new(|mode| Ok(Box::new(AsciiCodec::new(mode)?))));
+    codecs.insert("2bit".to_string(), Box::new(|mode| Ok(Box::new(TwoBitCodec::new(mode)?))));
+    codecs.insert("protein".to_string(), Box::new(|mode| Ok(Box::new(ProteinCodec::new(mode)?))));
+    codecs
+}
+
+pub fn default_codec(mode: &Mode) -> &'static str {
+    match mode {
+        Mode::DNA | Mode::RNA => DEFAULT_CODEC,
+        Mode::Protein => DEFAULT_PROTEIN_CODEC,
+    }
+}
+
+pub fn register_codec<F>(id: &str, factory: F)
+where
+    F: Fn(&Mode) -> Result<Box<dyn Codec>, CompressionError> + Send + Sync + 'static,
+{
+    assert!(id.len() <= u8::MAX as usize, "codec id must fit in the container header");
+    CODEC_REGISTRY
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(id.to_string(), Box::new(factory));
+}
+
+pub fn codec_for(id: &str, mode: &Mode) -> Result<Box<dyn Codec>, CompressionError> {
+    let registry = CODEC_REGISTRY.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    match registry.get(id) {
+        Some(factory) => factory(mode),
+        None => Err(CompressionError::UnknownCodec(id.to_string())),
+    }
+}
+
+pub fn available_codecs() -> Vec<String> {
+    let registry = CODEC_REGISTRY.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut ids: Vec<String> = registry.keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct VerbatimCodec;
+
+    impl Codec for VerbatimCodec {
+        fn id(&self) -> &str {
+            "verbatim"
+        }
+
+        fn encode(&self, sequence: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError> {
+            sink.extend_from_slice(sequence);
+            Ok(())
+        }
+
+        fn decode(&self, encoded: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError> {
+            sink.extend_from_slice(encoded);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_builtin_codecs() {
+        for id in ["ascii", "2bit"] {
+            for mode in [Mode::DNA, Mode::RNA] {
+                let codec = codec_for(id, &mode).unwrap();
+                assert_eq!(codec.id(), id);
+            }
+        }
+        assert!(available_codecs().contains(&DEFAULT_CODEC.to_string()));
+
+        let codec = codec_for("protein", &Mode::Protein).unwrap();
+        assert_eq!(codec.id(), "protein");
+        assert!(matches!(codec_for("ascii", &Mode::Protein), Err(CompressionError::UnsupportedMode { .. })));
+    }
+
+    #[test]
+    fn test_default_codec() {
+        for mode in [Mode::DNA, Mode::RNA, Mode::Protein] {
+            assert!(codec_for(default_codec(&mode), &mode).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_unknown_codec() {
+        assert!(matches!(codec_for("missing", &Mode::DNA), Err(CompressionError::UnknownCodec(id)) if id == "missing"));
+    }
+
+    #[test]
+    fn test_register_codec() {
+        register_codec("verbatim", |_| Ok(Box::new(VerbatimCodec)));
+        assert!(available_codecs().contains(&"verbatim".to_string()));
+
+        let codec = codec_for("verbatim", &Mode::DNA).unwrap();
+        let mut encoded = Vec::new();
+        codec.encode(b"ACGT", &mut encoded).unwrap();
+        let mut decoded = Vec::new();
+        codec.decode(&encoded, &mut decoded).unwrap();
+        assert_eq!(decoded, b"ACGT");
+    }
+}
@@ -0,0 +1,708 @@
+use std::fmt;
+use std::str::FromStr;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::errors::ContainerError;
+use crate::mode::Mode;
+use crate::quality::QualityBinning;
+use crate::varint::{read_varint_from, write_varint};
+
+pub const MAGIC: &[u8; 4] = b"CSEQ";
+pub const FORMAT_VERSION: u8 = 1;
+pub const FOOTER_LEN: u64 = 34;
+const CHECKSUM_LEN: u64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceFormat {
+    Text,
+    Fasta,
+    Fastq,
+}
+
+impl SourceFormat {
+    pub fn id(&self) -> u8 {
+        match self {
+            SourceFormat::Text => 0,
+            SourceFormat::Fasta => 1,
+            SourceFormat::Fastq => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(SourceFormat::Text),
+            1 => Some(SourceFormat::Fasta),
+            2 => Some(SourceFormat::Fastq),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for SourceFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(SourceFormat::Text),
+            "fasta" => Ok(SourceFormat::Fasta),
+            "fastq" => Ok(SourceFormat::Fastq),
+            _ => Err("Invalid format"),
+        }
+    }
+}
+
+impl fmt::Display for SourceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SourceFormat::Text => write!(f, "text"),
+            SourceFormat::Fasta => write!(f, "FASTA"),
+            SourceFormat::Fastq => write!(f, "FASTQ"),
+        }
+    }
+}
+
+// Lines are joined with this on unpack, so CRLF input round-trips byte for byte.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn id(&self) -> u8 {
+        match self {
+            LineEnding::Lf => 0,
+            LineEnding::CrLf => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(LineEnding::Lf),
+            1 => Some(LineEnding::CrLf),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    pub mode: Mode,
+    pub codec: String,
+    pub format: SourceFormat,
+    pub quality_binning: Option<QualityBinning>,
+    pub line_ending: LineEnding,
+}
+
+impl Header {
+    pub fn new(mode: &Mode, codec: &str, format: SourceFormat) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            mode: mode.clone(),
+            codec: codec.to_string(),
+            format,
+            quality_binning: None,
+            line_ending: LineEnding::Lf,
+        }
+    }
+
+    pub fn with_quality_binning(mut self, quality_binning: Option<&QualityBinning>) -> Self {
+        self.quality_binning = quality_binning.cloned();
+        self
+    }
+
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    pub fn is_lossy(&self) -> bool {
+        self.quality_binning.is_some()
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[self.version, self.mode.id(), self.format.id(), self.codec.len() as u8])?;
+        writer.write_all(self.codec.as_bytes())?;
+
+        let bins = self.quality_binning.as_ref().map_or(&[][..], |binning| binning.bins());
+        writer.write_all(&[bins.len() as u8])?;
+        for &(low, high, value) in bins {
+            writer.write_all(&[low, high, value])?;
+        }
+        writer.write_all(&[self.line_ending.id()])
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, ContainerError> {
+        let mut magic = [0u8; 4];
+        read_header_bytes(reader, &mut magic)?;
+        if &magic != MAGIC {
+            return Err(ContainerError::MissingHeader);
+        }
+
+        let mut fields = [0u8; 4];
+        read_header_bytes(reader, &mut fields)?;
+        let [version, mode_id, format_id, codec_len] = fields;
+        if version != FORMAT_VERSION {
+            return Err(ContainerError::UnsupportedVersion(version));
+        }
+        let mode = Mode::from_id(mode_id).ok_or(ContainerError::UnknownMode(mode_id))?;
+        let format = SourceFormat::from_id(format_id).ok_or(ContainerError::UnknownFormat(format_id))?;
+
+        let mut codec = vec![0u8; codec_len as usize];
+        read_header_bytes(reader, &mut codec)?;
+        let codec = String::from_utf8_lossy(&codec).into_owned();
+
+        let mut bin_count = [0u8; 1];
+        read_header_bytes(reader, &mut bin_count)?;
+        let mut bins = vec![0u8; 3 * bin_count[0] as usize];
+        read_header_bytes(reader, &mut bins)?;
+        let quality_binning = match bin_count[0] {
+            0 => None,
+            _ => {
+                let bins = bins.chunks(3).map(|bin| (bin[0], bin[1], bin[2])).collect();
+                Some(QualityBinning::new(bins).map_err(|_| ContainerError::InvalidQualityBinning)?)
+            }
+        };
+
+        let mut line_ending = [0u8; 1];
+        read_header_bytes(reader, &mut line_ending)?;
+        let line_ending = LineEnding::from_id(line_ending[0]).ok_or(ContainerError::UnknownLineEnding(line_ending[0]))?;
+
+        Ok(Self {
+            version,
+            mode,
+            codec,
+            format,
+            quality_binning,
+            line_ending,
+        })
+    }
+
+    pub fn validate(&self, format: SourceFormat, mode: Option<&Mode>) -> Result<(), ContainerError> {
+        if self.format != format {
+            return Err(ContainerError::FormatMismatch { expected: format, found: self.format });
+        }
+        match mode {
+            Some(mode) if mode != &self.mode => {
+                Err(ContainerError::ModeMismatch { expected: mode.clone(), found: self.mode.clone() })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordKind {
+    Sequence,
+    Raw,
+    MaskedSequence,
+    Quality,
+    WrappedSequence,
+    Index,
+    Footer,
+    Summary,
+}
+
+impl RecordKind {
+    pub fn id(&self) -> u8 {
+        match self {
+            RecordKind::Sequence => 0,
+            RecordKind::Raw => 1,
+            RecordKind::MaskedSequence => 2,
+            RecordKind::Quality => 3,
+            RecordKind::WrappedSequence => 4,
+            RecordKind::Index => 5,
+            RecordKind::Footer => 6,
+            RecordKind::Summary => 7,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(RecordKind::Sequence),
+            1 => Some(RecordKind::Raw),
+            2 => Some(RecordKind::MaskedSequence),
+            3 => Some(RecordKind::Quality),
+            4 => Some(RecordKind::WrappedSequence),
+            5 => Some(RecordKind::Index),
+            6 => Some(RecordKind::Footer),
+            7 => Some(RecordKind::Summary),
+            _ => None,
+        }
+    }
+
+    pub fn is_metadata(&self) -> bool {
+        matches!(self, RecordKind::Index | RecordKind::Footer | RecordKind::Summary)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub kind: RecordKind,
+    pub payload: Vec<u8>,
+}
+
+impl Record {
+    pub fn new(kind: RecordKind, payload: Vec<u8>) -> Self {
+        Self { kind, payload }
+    }
+
+    pub fn encoded_len(&self) -> u64 {
+        self.prefix().len() as u64 + self.payload.len() as u64 + CHECKSUM_LEN
+    }
+
+    // Every record ends with the CRC32 of its tag, length and payload.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let prefix = self.prefix();
+        writer.write_all(&prefix)?;
+        writer.write_all(&self.payload)?;
+        writer.write_all(&record_checksum(&prefix, &self.payload).to_le_bytes())
+    }
+
+    fn prefix(&self) -> Vec<u8> {
+        let mut prefix = vec![self.kind.id()];
+        write_varint(&mut prefix, self.payload.len() as u64);
+        prefix
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Option<Self>, ContainerError> {
+        match Self::read_unverified(reader)? {
+            Some((record, false)) => Err(ContainerError::RecordChecksumMismatch(format!("{:?} record", record.kind))),
+            Some((record, true)) => Ok(Some(record)),
+            None => Ok(None),
+        }
+    }
+
+    // Also returns whether the stored checksum matched, so a reader can name
+    // the corrupt record before failing.
+    fn read_unverified<R: Read>(reader: &mut R) -> Result<Option<(Self, bool)>, ContainerError> {
+        let mut tag = [0u8; 1];
+        if reader.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+        let kind = RecordKind::from_id(tag[0]).ok_or(ContainerError::UnknownRecord(tag[0]))?;
+
+        let len = read_varint_from(reader).map_err(truncated)?;
+        let mut payload = Vec::new();
+        reader.take(len).read_to_end(&mut payload)?;
+        if payload.len() as u64 != len {
+            return Err(ContainerError::Truncated);
+        }
+
+        let mut checksum = [0u8; CHECKSUM_LEN as usize];
+        reader.read_exact(&mut checksum).map_err(truncated)?;
+        let record = Self { kind, payload };
+        let verified = u32::from_le_bytes(checksum) == record_checksum(&record.prefix(), &record.payload);
+        Ok(Some((record, verified)))
+    }
+
+    pub fn read_all<R: Read>(reader: &mut R) -> Result<Vec<Self>, ContainerError> {
+        let mut records = Vec::new();
+        while let Some(record) = Self::read_from(reader)? {
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+// The footer is the last record of every archive and always FOOTER_LEN bytes
+// long, so readers can find the index and summary by seeking from the end. It
+// also stores how many records precede it and the CRC32 of the unpacked content.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Footer {
+    pub index_offset: Option<u64>,
+    pub summary_offset: Option<u64>,
+    pub record_count: u64,
+    pub content_checksum: u32,
+}
+
+impl Footer {
+    pub fn to_record(&self) -> Record {
+        let mut payload = self.index_offset.unwrap_or(0).to_le_bytes().to_vec();
+        payload.extend_from_slice(&self.summary_offset.unwrap_or(0).to_le_bytes());
+        payload.extend_from_slice(&self.record_count.to_le_bytes());
+        payload.extend_from_slice(&self.content_checksum.to_le_bytes());
+        Record::new(RecordKind::Footer, payload)
+    }
+
+    pub fn from_record(record: &Record) -> Result<Self, ContainerError> {
+        if record.kind != RecordKind::Footer || record.payload.len() != 28 {
+            return Err(ContainerError::Truncated);
+        }
+        let field = |start: usize| u64::from_le_bytes(record.payload[start..start + 8].try_into().unwrap());
+        // The header always comes first, so an offset of zero means "not present".
+        let offset = |start: usize| Some(field(start)).filter(|&offset| offset > 0);
+        Ok(Self {
+            index_offset: offset(0),
+            summary_offset: offset(8),
+            record_count: field(16),
+            content_checksum: u32::from_le_bytes(record.payload[24..].try_into().unwrap()),
+        })
+    }
+}
+
+pub fn read_footer<R: Read + Seek>(reader: &mut R) -> Result<Footer, ContainerError> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    if end < FOOTER_LEN {
+        return Err(ContainerError::Truncated);
+    }
+    let record = read_record_at(reader, end - FOOTER_LEN).map_err(|err| match err {
+        ContainerError::Io(err) => ContainerError::Io(err),
+        _ => ContainerError::Truncated,
+    })?;
+    Footer::from_record(&record)
+}
+
+pub fn read_index_offset<R: Read + Seek>(reader: &mut R) -> Result<u64, ContainerError> {
+    read_footer(reader)?.index_offset.ok_or(ContainerError::MissingIndex)
+}
+
+pub fn read_record_at<R: Read + Seek>(reader: &mut R, offset: u64) -> Result<Record, ContainerError> {
+    reader.seek(SeekFrom::Start(offset))?;
+    match Record::read_from(reader) {
+        Err(ContainerError::RecordChecksumMismatch(record)) => {
+            Err(ContainerError::RecordChecksumMismatch(format!("{} at offset {}", record, offset)))
+        }
+        result => result?.ok_or(ContainerError::Truncated),
+    }
+}
+
+// Writes the header and then records in order, keeping track of their
+// offsets and count so the footer can be filled in at the end.
+pub struct ArchiveWriter<W: Write> {
+    inner: W,
+    offset: u64,
+    footer: Footer,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    pub fn new(mut inner: W, header: &Header) -> io::Result<Self> {
+        let mut header_bytes = Vec::new();
+        header.write_to(&mut header_bytes)?;
+        inner.write_all(&header_bytes)?;
+        Ok(Self { inner, offset: header_bytes.len() as u64, footer: Footer::default() })
+    }
+
+    // Returns the offset the record was written at.
+    pub fn write_record(&mut self, record: &Record) -> io::Result<u64> {
+        let offset = self.offset;
+        record.write_to(&mut self.inner)?;
+        self.offset += record.encoded_len();
+        self.footer.record_count += 1;
+        Ok(offset)
+    }
+
+    pub fn footer_mut(&mut self) -> &mut Footer {
+        &mut self.footer
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.footer.to_record().write_to(&mut self.inner)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+// Reads the records following the header in order, numbering them from one so
+// a corrupt record can be named, together with the FASTA or FASTQ header it
+// belongs to. Reaching the end of the input before the footer means the
+// archive was truncated.
+pub struct RecordReader<R: Read> {
+    inner: R,
+    records: u64,
+    sequence_name: Option<String>,
+    summary: Option<Record>,
+    footer: Option<Footer>,
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, records: 0, sequence_name: None, summary: None, footer: None }
+    }
+
+    // Returns None once the footer has been read.
+    pub fn next_record(&mut self) -> Result<Option<Record>, ContainerError> {
+        if self.footer.is_some() {
+            return Ok(None);
+        }
+        let record = match Record::read_unverified(&mut self.inner)? {
+            Some((record, true)) => record,
+            Some((record, false)) => return Err(ContainerError::RecordChecksumMismatch(self.describe(&record))),
+            None => return Err(ContainerError::Truncated),
+        };
+        if record.kind == RecordKind::Raw && matches!(record.payload.first(), Some(b'>' | b'@')) {
+            let name = record.payload.split(u8::is_ascii_whitespace).next().unwrap_or_default();
+            self.sequence_name = Some(String::from_utf8_lossy(name).into_owned());
+        }
+        if record.kind == RecordKind::Summary {
+            self.summary = Some(record.clone());
+        }
+        if record.kind != RecordKind::Footer {
+            self.records += 1;
+            return Ok(Some(record));
+        }
+
+        let footer = Footer::from_record(&record)?;
+        if footer.record_count != self.records {
+            return Err(ContainerError::RecordCountMismatch { expected: footer.record_count, found: self.records });
+        }
+        self.footer = Some(footer);
+        Ok(None)
+    }
+
+    // A corrupt header line is not named after the sequence before it.
+    fn describe(&self, record: &Record) -> String {
+        match (&self.sequence_name, record.kind) {
+            (Some(name), kind) if kind != RecordKind::Raw => format!("{:?} record {} ({})", kind, self.records + 1, name),
+            _ => format!("{:?} record {}", record.kind, self.records + 1),
+        }
+    }
+
+    pub fn summary(&self) -> Option<&Record> {
+        self.summary.as_ref()
+    }
+
+    pub fn footer(&self) -> Option<&Footer> {
+        self.footer.as_ref()
+    }
+
+    pub fn verify_content(&self, checksum: u32) -> Result<(), ContainerError> {
+        match self.footer {
+            Some(footer) if footer.content_checksum == checksum => Ok(()),
+            Some(_) => Err(ContainerError::ContentChecksumMismatch),
+            None => Err(ContainerError::Truncated),
+        }
+    }
+}
+
+fn record_checksum(prefix: &[u8], payload: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(prefix);
+    hasher.update(payload);
+    hasher.finalize()
+}
+
+fn truncated(err: io::Error) -> ContainerError {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => ContainerError::Truncated,
+        _ => ContainerError::Io(err),
+    }
+}
+
+fn read_header_bytes<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), ContainerError> {
+    reader.read_exact(buf).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => ContainerError::MissingHeader,
+        _ => ContainerError::Io(err),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = Header::new(&Mode::RNA, "2bit", SourceFormat::Fasta);
+        let mut bytes = Vec::new();
+        header.write_to(&mut bytes).unwrap();
+
+        assert!(bytes.starts_with(MAGIC));
+        assert_eq!(Header::read_from(&mut bytes.as_slice()).unwrap(), header);
+        assert!(!header.is_lossy());
+
+        let header = Header::new(&Mode::DNA, "ascii", SourceFormat::Fastq)
+            .with_quality_binning(Some(&QualityBinning::illumina()))
+            .with_line_ending(LineEnding::CrLf);
+        let mut bytes = Vec::new();
+        header.write_to(&mut bytes).unwrap();
+        let decoded = Header::read_from(&mut bytes.as_slice()).unwrap();
+        assert!(decoded.is_lossy());
+        assert_eq!(decoded.line_ending, LineEnding::CrLf);
+        assert_eq!(decoded, header);
+
+        let last = bytes.len() - 1;
+        bytes[last] = 2;
+        assert!(matches!(Header::read_from(&mut bytes.as_slice()), Err(ContainerError::UnknownLineEnding(2))));
+        bytes.truncate(last);
+
+        let bin = bytes.len() - 3;
+        bytes[bin..].copy_from_slice(&[50, 40, 45]);
+        assert!(matches!(Header::read_from(&mut bytes.as_slice()), Err(ContainerError::InvalidQualityBinning)));
+    }
+
+    #[test]
+    fn test_missing_header() {
+        let result = Header::read_from(&mut "AAACCCGGG".as_bytes());
+        assert!(matches!(result, Err(ContainerError::MissingHeader)));
+
+        let result = Header::read_from(&mut "CS".as_bytes());
+        assert!(matches!(result, Err(ContainerError::MissingHeader)));
+    }
+
+    #[test]
+    fn test_truncated_header() {
+        let mut bytes = Vec::new();
+        Header::new(&Mode::DNA, "ascii", SourceFormat::Text).write_to(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+
+        let result = Header::read_from(&mut bytes.as_slice());
+        assert!(matches!(result, Err(ContainerError::MissingHeader)));
+    }
+
+    #[test]
+    fn test_unknown_header_fields() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[FORMAT_VERSION + 1, 0, 0, 0]);
+        let result = Header::read_from(&mut bytes.as_slice());
+        assert!(matches!(result, Err(ContainerError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1));
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[FORMAT_VERSION, 9, 0, 0]);
+        let result = Header::read_from(&mut bytes.as_slice());
+        assert!(matches!(result, Err(ContainerError::UnknownMode(9))));
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[FORMAT_VERSION, 0, 9, 0]);
+        let result = Header::read_from(&mut bytes.as_slice());
+        assert!(matches!(result, Err(ContainerError::UnknownFormat(9))));
+    }
+
+    #[test]
+    fn test_validate() {
+        let header = Header::new(&Mode::DNA, "ascii", SourceFormat::Text);
+        assert!(header.validate(SourceFormat::Text, Some(&Mode::DNA)).is_ok());
+        assert!(header.validate(SourceFormat::Text, None).is_ok());
+        assert!(matches!(
+            header.validate(SourceFormat::Fasta, None),
+            Err(ContainerError::FormatMismatch { .. })
+        ));
+        assert!(matches!(
+            header.validate(SourceFormat::Text, Some(&Mode::RNA)),
+            Err(ContainerError::ModeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let records = vec![
+            Record::new(RecordKind::Raw, b">desc1".to_vec()),
+            Record::new(RecordKind::Sequence, vec![0, b'\n', 255]),
+            Record::new(RecordKind::Sequence, Vec::new()),
+        ];
+        let mut bytes = Vec::new();
+        for record in &records {
+            record.write_to(&mut bytes).unwrap();
+        }
+
+        assert_eq!(Record::read_all(&mut bytes.as_slice()).unwrap(), records);
+    }
+
+    #[test]
+    fn test_truncated_record() {
+        let mut bytes = Vec::new();
+        Record::new(RecordKind::Sequence, b"ACGT".to_vec()).write_to(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(Record::read_all(&mut bytes.as_slice()), Err(ContainerError::Truncated)));
+
+        assert!(matches!(Record::read_all(&mut [9u8, 0].as_slice()), Err(ContainerError::UnknownRecord(9))));
+    }
+
+    #[test]
+    fn test_footer() {
+        let mut bytes = Vec::new();
+        Header::new(&Mode::DNA, "ascii", SourceFormat::Fasta).write_to(&mut bytes).unwrap();
+        let index_offset = bytes.len() as u64;
+        let index = Record::new(RecordKind::Index, vec![1, 2, 3]);
+        index.write_to(&mut bytes).unwrap();
+        let footer = Footer { index_offset: Some(index_offset), summary_offset: Some(3), record_count: 1, content_checksum: 0xdeadbeef };
+        assert_eq!(footer.to_record().encoded_len(), FOOTER_LEN);
+        footer.to_record().write_to(&mut bytes).unwrap();
+
+        let mut reader = io::Cursor::new(bytes);
+        assert_eq!(read_footer(&mut reader).unwrap(), footer);
+        let offset = read_index_offset(&mut reader).unwrap();
+        assert_eq!(offset, index_offset);
+        assert_eq!(read_record_at(&mut reader, offset).unwrap(), index);
+
+        let mut unindexed = Vec::new();
+        Footer::default().to_record().write_to(&mut unindexed).unwrap();
+        assert!(matches!(read_index_offset(&mut io::Cursor::new(unindexed)), Err(ContainerError::MissingIndex)));
+        assert!(matches!(read_index_offset(&mut io::Cursor::new(vec![0u8; 4])), Err(ContainerError::Truncated)));
+    }
+
+    fn sample_records() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        Record::new(RecordKind::Raw, b">desc1".to_vec()).write_to(&mut bytes).unwrap();
+        Record::new(RecordKind::Sequence, b"ACGT".to_vec()).write_to(&mut bytes).unwrap();
+        Footer { record_count: 2, content_checksum: 7, ..Footer::default() }.to_record().write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_archive_writer() {
+        let header = Header::new(&Mode::DNA, "ascii", SourceFormat::Text);
+        let mut writer = ArchiveWriter::new(Vec::new(), &header).unwrap();
+        let first = writer.write_record(&Record::new(RecordKind::Raw, b">desc1".to_vec())).unwrap();
+        let second = writer.write_record(&Record::new(RecordKind::Sequence, b"ACGT".to_vec())).unwrap();
+        writer.footer_mut().content_checksum = 7;
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = io::Cursor::new(bytes);
+        assert_eq!(Header::read_from(&mut reader).unwrap(), header);
+        assert_eq!(reader.position(), first);
+        assert_eq!(read_record_at(&mut reader, second).unwrap().payload, b"ACGT");
+        assert_eq!(read_footer(&mut reader).unwrap(), Footer { record_count: 2, content_checksum: 7, ..Footer::default() });
+    }
+
+    #[test]
+    fn test_record_reader() {
+        let bytes = sample_records();
+        let mut reader = RecordReader::new(bytes.as_slice());
+        assert_eq!(reader.next_record().unwrap().unwrap().kind, RecordKind::Raw);
+        assert_eq!(reader.next_record().unwrap().unwrap().kind, RecordKind::Sequence);
+        assert!(reader.next_record().unwrap().is_none());
+        assert!(reader.next_record().unwrap().is_none());
+        assert!(reader.verify_content(7).is_ok());
+        assert!(matches!(reader.verify_content(8), Err(ContainerError::ContentChecksumMismatch)));
+    }
+
+    #[test]
+    fn test_record_reader_detects_corruption() {
+        let mut bytes = sample_records();
+        bytes[15] ^= 0x01;
+        let mut reader = RecordReader::new(bytes.as_slice());
+        reader.next_record().unwrap();
+        let err = reader.next_record().unwrap_err();
+        assert!(matches!(&err, ContainerError::RecordChecksumMismatch(record) if record == "Sequence record 2 (>desc1)"));
+        assert_eq!(err.to_string(), "Checksum mismatch in Sequence record 2 (>desc1) of compressed file");
+
+        let mut bytes = sample_records();
+        bytes[3] ^= 0x01;
+        let err = RecordReader::new(bytes.as_slice()).next_record().unwrap_err();
+        assert!(matches!(&err, ContainerError::RecordChecksumMismatch(record) if record == "Raw record 1"));
+
+        let bytes = sample_records();
+        let truncated = &bytes[..bytes.len() - FOOTER_LEN as usize];
+        let mut reader = RecordReader::new(truncated);
+        reader.next_record().unwrap();
+        reader.next_record().unwrap();
+        assert!(matches!(reader.next_record(), Err(ContainerError::Truncated)));
+        assert!(matches!(reader.verify_content(7), Err(ContainerError::Truncated)));
+
+        let mut dropped = bytes[..12].to_vec();
+        dropped.extend_from_slice(&bytes[bytes.len() - FOOTER_LEN as usize..]);
+        let mut reader = RecordReader::new(dropped.as_slice());
+        reader.next_record().unwrap();
+        assert!(matches!(reader.next_record(), Err(ContainerError::RecordCountMismatch { expected: 2, found: 1 })));
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use crate::codec::Codec;
+use crate::errors::CompressionError;
+use crate::mode::Mode;
+use super::{dna_to_ascii, rna_to_ascii};
+use super::iupac::is_ambiguity_code;
+
+const AMBIGUITY_ESCAPE: char = '~';
+
+pub struct AsciiCodec {
+    encoding_map: &'static HashMap<String, String>,
+    decoding_map: &'static HashMap<String, String>,
+}
+
+impl AsciiCodec {
+    pub fn new(mode: &Mode) -> Result<Self, CompressionError> {
+        let (encoding_map, decoding_map) = match mode {
+            Mode::DNA => (&*dna_to_ascii::DNA_ENCODING_MAP, &*dna_to_ascii::DNA_DECODING_MAP),
+            Mode::RNA => (&*rna_to_ascii::RNA_ENCODING_MAP, &*rna_to_ascii::RNA_DECODING_MAP),
+            Mode::Protein => {
+                return Err(CompressionError::UnsupportedMode { codec: "ascii".to_string(), mode: mode.clone() })
+            }
+        };
+        Ok(Self { encoding_map, decoding_map })
+    }
+
+    fn encoding_map(&self) -> &'static HashMap<String, String> {
+        self.encoding_map
+    }
+
+    fn decoding_map(&self) -> &'static HashMap<String, String> {
+        self.decoding_map
+    }
+
+    fn compress_string(&self, input: &[u8]) -> Result<String, CompressionError> {
+        let encoding_map = self.encoding_map();
+
+        let input = input.to_ascii_uppercase();
+        let mut compressed = String::new();
+
+        for chunk in input.chunks(3) {
+            let key = String::from_utf8_lossy(chunk).into_owned();
+            if let Some(encoded_value) = encoding_map.get(&key) {
+                compressed.push_str(encoded_value);
+            } else if chunk.iter().any(|&base| is_ambiguity_code(base)) {
+                self.compress_ambiguous_chunk(chunk, &mut compressed)?;
+            } else {
+                return Err(CompressionError::UnknownSequence(key));
+            }
+        }
+
+        Ok(compressed)
+    }
+
+    fn compress_ambiguous_chunk(&self, chunk: &[u8], compressed: &mut String) -> Result<(), CompressionError> {
+        let encoding_map = self.encoding_map();
+
+        for &base in chunk {
+            let key = (base as char).to_string();
+            if is_ambiguity_code(base) {
+                compressed.push(AMBIGUITY_ESCAPE);
+                compressed.push(base as char);
+            } else if let Some(encoded_value) = encoding_map.get(&key) {
+                compressed.push_str(encoded_value);
+            } else {
+                return Err(CompressionError::UnknownSequence(String::from_utf8_lossy(chunk).into_owned()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn unpack_string(&self, input: &str) -> Result<String, CompressionError> {
+        let decoding_map = self.decoding_map();
+
+        let mut unpacked = String::new();
+        let mut previous_was_exclamation = false;
+        let mut previous_was_ambiguity_escape = false;
+
+        for ch in input.chars() {
+            if previous_was_ambiguity_escape {
+                previous_was_ambiguity_escape = false;
+                if !ch.is_ascii() || !is_ambiguity_code(ch as u8) {
+                    return Err(CompressionError::UnknownCharacter(ch));
+                }
+                unpacked.push(ch);
+                continue;
+            }
+
+            if ch == AMBIGUITY_ESCAPE {
+                previous_was_ambiguity_escape = true;
+                continue;
+            }
+
+            if ch == '!' {
+                previous_was_exclamation = true;
+                continue;
+            }
+
+            let key = if previous_was_exclamation {
+                previous_was_exclamation = false;
+                format!("!{}", ch)
+            } else {
+                ch.to_string()
+            };
+
+            if let Some(decoded_value) = decoding_map.get(&key) {
+                unpacked.push_str(decoded_value);
+            } else {
+                return Err(CompressionError::UnknownCharacter(ch));
+            }
+        }
+
+        Ok(unpacked)
+    }
+}
+
+impl Codec for AsciiCodec {
+    fn id(&self) -> &str {
+        "ascii"
+    }
+
+    fn encode(&self, sequence: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError> {
+        sink.extend_from_slice(self.compress_string(sequence)?.as_bytes());
+        Ok(())
+    }
+
+    fn decode(&self, encoded: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError> {
+        sink.extend_from_slice(self.unpack_string(&String::from_utf8_lossy(encoded))?.as_bytes());
+        Ok(())
+    }
+
+    fn chunk_alignment(&self) -> Option<usize> {
+        Some(3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DNA_TEST_MODE: Mode = Mode::DNA;
+    const RNA_TEST_MODE: Mode = Mode::RNA;
+
+    #[test]
+    fn test_dna_compress_string() {
+        let codec = AsciiCodec::new(&DNA_TEST_MODE).unwrap();
+        let test_strings = vec!["AAAA", "AC", "AAAACCCGTT", "AGGGGCCCCTTTTAA", ""];
+        for s in test_strings {
+            let compressed = codec.compress_string(s.as_bytes()).unwrap();
+            let expected_len = s.len().div_ceil(3);
+            assert_eq!(compressed.len(), expected_len);
+        }
+    }
+
+    #[test]
+    fn test_dna_unpack_string() {
+        let codec = AsciiCodec::new(&DNA_TEST_MODE).unwrap();
+        let test_strings = vec!["A", "Aq1", "123", "5", ""];
+        for s in test_strings {
+            let unpacked = codec.unpack_string(s).unwrap();
+            assert!(unpacked.len() >= s.len());
+        }
+    }
+
+    #[test]
+    fn test_rna_compress_string() {
+        let codec = AsciiCodec::new(&RNA_TEST_MODE).unwrap();
+        let test_strings = vec!["AAAA", "AC", "AAANNNACCCGUU", "AGGNNNGGCCCCUUUAA", ""];
+        for s in test_strings {
+            let compressed = codec.compress_string(s.as_bytes()).unwrap();
+            let expected_len = s.len().div_ceil(3);
+            assert_eq!(compressed.len(), expected_len);
+        }
+    }
+
+    #[test]
+    fn test_rna_unpack_string() {
+        let codec = AsciiCodec::new(&RNA_TEST_MODE).unwrap();
+        let test_strings = vec!["A", "Aq1", "123", "5", ""];
+        for s in test_strings {
+            let unpacked = codec.unpack_string(s).unwrap();
+            assert!(unpacked.len() >= s.len());
+        }
+    }
+
+    #[test]
+    fn test_ambiguity_codes() {
+        for mode in [DNA_TEST_MODE, RNA_TEST_MODE] {
+            let codec = AsciiCodec::new(&mode).unwrap();
+            let plain = if mode == DNA_TEST_MODE { "ACGTTGCA" } else { "ACGUUGCA" };
+            let sequence = format!("{}RYSWKMBDHV{}NAR", plain, plain);
+
+            let compressed = codec.compress_string(sequence.as_bytes()).unwrap();
+            assert_eq!(codec.unpack_string(&compressed).unwrap(), sequence);
+            assert_eq!(codec.compress_string(plain.as_bytes()).unwrap().len(), plain.len().div_ceil(3));
+        }
+    }
+
+    #[test]
+    fn test_ambiguity_escape_is_unused_by_maps() {
+        for codec in [AsciiCodec::new(&DNA_TEST_MODE).unwrap(), AsciiCodec::new(&RNA_TEST_MODE).unwrap()] {
+            assert!(codec.encoding_map().values().all(|value| !value.contains(AMBIGUITY_ESCAPE)));
+        }
+    }
+
+    #[test]
+    fn test_invalid_ambiguity_escape() {
+        let codec = AsciiCodec::new(&DNA_TEST_MODE).unwrap();
+        assert!(matches!(codec.unpack_string("~A"), Err(CompressionError::UnknownCharacter('A'))));
+        assert!(matches!(codec.compress_string(b"AR-"), Err(CompressionError::UnknownSequence(_))));
+    }
+
+    #[test]
+    fn test_protein_mode_is_unsupported() {
+        assert!(matches!(AsciiCodec::new(&Mode::Protein), Err(CompressionError::UnsupportedMode { .. })));
+    }
+
+    #[test]
+    fn test_codec_round_trip() {
+        let codec = AsciiCodec::new(&DNA_TEST_MODE).unwrap();
+        let mut encoded = Vec::new();
+        codec.encode(b"GATTACANNNAC", &mut encoded).unwrap();
+        let mut decoded = Vec::new();
+        codec.decode(&encoded, &mut decoded).unwrap();
+        assert_eq!(decoded, b"GATTACANNNAC");
+        assert_eq!(codec.id(), "ascii");
+    }
+}
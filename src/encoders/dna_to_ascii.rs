@@ -135,7 +135,7 @@ mod tests {
             let value_string = chars.iter().collect::<String>();
             assert!(chars.len() == 1 || value_string.starts_with("!"));
             let ascii_value = chars[0] as u32;
-            assert!(ascii_value >= 32 && ascii_value <= 126);
+            assert!((32..=126).contains(&ascii_value));
         }
     }
 
@@ -162,7 +162,7 @@ mod tests {
         assert_eq!(decoding_map.get(&encoding_map["G"]), Some(&"G".to_string()));
         assert_eq!(decoding_map.get(&encoding_map["T"]), Some(&"T".to_string()));
 
-        assert!(!encoding_map.contains_key(&"U".to_string()));
+        assert!(!encoding_map.contains_key("U"));
 
         assert_eq!(decoding_map.get(&encoding_map["N"]), Some(&"N".to_string()));
 
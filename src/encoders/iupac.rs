@@ -0,0 +1,20 @@
+pub const AMBIGUITY_CODES: &[u8; 10] = b"RYSWKMBDHV";
+
+pub fn is_ambiguity_code(base: u8) -> bool {
+    AMBIGUITY_CODES.contains(&base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ambiguity_code() {
+        for &code in AMBIGUITY_CODES {
+            assert!(is_ambiguity_code(code));
+        }
+        for &base in b"ACGTUN>!~r" {
+            assert!(!is_ambiguity_code(base));
+        }
+    }
+}
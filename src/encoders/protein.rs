@@ -0,0 +1,138 @@
+use crate::codec::Codec;
+use crate::errors::CompressionError;
+use crate::mode::Mode;
+use crate::varint::{read_varint, write_varint};
+
+pub const AMINO_ACIDS: &[u8; 26] = b"ACDEFGHIKLMNPQRSTVWYXBZUO*";
+
+const BITS_PER_RESIDUE: usize = 5;
+
+lazy_static::lazy_static! {
+    static ref ENCODING_TABLE: [Option<u8>; 256] = create_encoding_table();
+}
+
+pub struct ProteinCodec;
+
+impl ProteinCodec {
+    pub fn new(mode: &Mode) -> Result<Self, CompressionError> {
+        match mode {
+            Mode::Protein => Ok(Self),
+            _ => Err(CompressionError::UnsupportedMode { codec: "protein".to_string(), mode: mode.clone() }),
+        }
+    }
+}
+
+impl Codec for ProteinCodec {
+    fn id(&self) -> &str {
+        "protein"
+    }
+
+    fn encode(&self, sequence: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError> {
+        write_varint(sink, sequence.len() as u64);
+
+        let mut buffer = 0u32;
+        let mut buffered_bits = 0;
+        for &residue in sequence {
+            let code = ENCODING_TABLE[residue.to_ascii_uppercase() as usize]
+                .ok_or_else(|| CompressionError::UnknownSequence((residue as char).to_string()))?;
+            buffer = (buffer << BITS_PER_RESIDUE) | code as u32;
+            buffered_bits += BITS_PER_RESIDUE;
+            while buffered_bits >= 8 {
+                buffered_bits -= 8;
+                sink.push((buffer >> buffered_bits) as u8);
+            }
+        }
+        if buffered_bits > 0 {
+            sink.push((buffer << (8 - buffered_bits)) as u8);
+        }
+
+        Ok(())
+    }
+
+    fn decode(&self, encoded: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError> {
+        let mut input = encoded;
+        let len = read_varint(&mut input)
+            .ok_or_else(|| CompressionError::InvalidEncoding("truncated length".to_string()))? as usize;
+        if Some(input.len()) != len.checked_mul(BITS_PER_RESIDUE).map(|bits| bits.div_ceil(8)) {
+            return Err(CompressionError::InvalidEncoding("truncated packed residues".to_string()));
+        }
+
+        sink.reserve(len);
+        let mut buffer = 0u32;
+        let mut buffered_bits = 0;
+        let mut remaining = len;
+        for &byte in input {
+            buffer = (buffer << 8) | byte as u32;
+            buffered_bits += 8;
+            while buffered_bits >= BITS_PER_RESIDUE && remaining > 0 {
+                buffered_bits -= BITS_PER_RESIDUE;
+                let code = ((buffer >> buffered_bits) & 0b11111) as usize;
+                let residue = AMINO_ACIDS
+                    .get(code)
+                    .ok_or_else(|| CompressionError::InvalidEncoding(format!("unknown residue code {}", code)))?;
+                sink.push(*residue);
+                remaining -= 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn create_encoding_table() -> [Option<u8>; 256] {
+    let mut table = [None; 256];
+    for (code, &residue) in AMINO_ACIDS.iter().enumerate() {
+        table[residue as usize] = Some(code as u8);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(sequence: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let codec = ProteinCodec::new(&Mode::Protein).unwrap();
+        let mut encoded = Vec::new();
+        codec.encode(sequence, &mut encoded).unwrap();
+        let mut decoded = Vec::new();
+        codec.decode(&encoded, &mut decoded).unwrap();
+        (encoded, decoded)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let test_strings: [&[u8]; 5] = [b"", b"M", b"MKV", b"MKVLAAGIXBZUO*", AMINO_ACIDS];
+        for sequence in test_strings {
+            let (_, decoded) = round_trip(sequence);
+            assert_eq!(decoded, sequence);
+        }
+    }
+
+    #[test]
+    fn test_packs_five_bits_per_residue() {
+        let sequence = AMINO_ACIDS.repeat(40);
+        let (encoded, _) = round_trip(&sequence);
+        assert!(encoded.len() <= sequence.len() * 5 / 8 + 3);
+    }
+
+    #[test]
+    fn test_unknown_residue() {
+        let codec = ProteinCodec::new(&Mode::Protein).unwrap();
+        let result = codec.encode(b"MKJ", &mut Vec::new());
+        assert!(matches!(result, Err(CompressionError::UnknownSequence(residue)) if residue == "J"));
+    }
+
+    #[test]
+    fn test_invalid_encoding() {
+        let codec = ProteinCodec::new(&Mode::Protein).unwrap();
+        assert!(matches!(codec.decode(&[4, 0], &mut Vec::new()), Err(CompressionError::InvalidEncoding(_))));
+        assert!(matches!(codec.decode(&[1, 0xff], &mut Vec::new()), Err(CompressionError::InvalidEncoding(_))));
+    }
+
+    #[test]
+    fn test_nucleotide_modes_are_unsupported() {
+        assert!(matches!(ProteinCodec::new(&Mode::DNA), Err(CompressionError::UnsupportedMode { .. })));
+        assert!(matches!(ProteinCodec::new(&Mode::RNA), Err(CompressionError::UnsupportedMode { .. })));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::codec::Codec;
+use crate::errors::CompressionError;
+use crate::mode::Mode;
+use crate::varint::{read_varint, write_varint};
+use super::iupac::is_ambiguity_code;
+use rayon::prelude::*;
+
+const DNA_BASES: &[u8; 4] = b"ACGT";
+const RNA_BASES: &[u8; 4] = b"ACGU";
+const PARALLEL_CHUNK_BASES: usize = 1 << 20;
+
+lazy_static::lazy_static! {
+    static ref DNA_DECODING_TABLE: [[u8; 4]; 256] = create_decoding_table(DNA_BASES);
+    static ref RNA_DECODING_TABLE: [[u8; 4]; 256] = create_decoding_table(RNA_BASES);
+}
+
+pub struct TwoBitCodec {
+    mode: Mode,
+}
+
+impl TwoBitCodec {
+    pub fn new(mode: &Mode) -> Result<Self, CompressionError> {
+        bases(mode)?;
+        Ok(Self { mode: mode.clone() })
+    }
+}
+
+impl Codec for TwoBitCodec {
+    fn id(&self) -> &str {
+        "2bit"
+    }
+
+    fn encode(&self, sequence: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError> {
+        sink.extend_from_slice(&encode(sequence, &self.mode)?);
+        Ok(())
+    }
+
+    fn decode(&self, encoded: &[u8], sink: &mut Vec<u8>) -> Result<(), CompressionError> {
+        sink.extend_from_slice(&decode(encoded, &self.mode)?);
+        Ok(())
+    }
+}
+
+// Layout: base count, exception count, exceptions as (gap, run length, byte),
+// then the packed bases, four per byte, most significant bits first.
+// Positions covered by an exception are packed as zeroes.
+pub fn encode(sequence: &[u8], mode: &Mode) -> Result<Vec<u8>, CompressionError> {
+    let bases = bases(mode)?;
+    let mut packed = vec![0u8; sequence.len().div_ceil(4)];
+
+    let chunk_exceptions: Vec<Vec<(usize, usize, u8)>> = packed
+        .par_chunks_mut(PARALLEL_CHUNK_BASES / 4)
+        .zip(sequence.par_chunks(PARALLEL_CHUNK_BASES))
+        .enumerate()
+        .map(|(i, (packed, chunk))| pack_chunk(chunk, i * PARALLEL_CHUNK_BASES, bases, packed))
+        .collect::<Result<_, _>>()?;
+
+    let mut exceptions: Vec<(usize, usize, u8)> = Vec::new();
+    for (start, len, base) in chunk_exceptions.into_iter().flatten() {
+        match exceptions.last_mut() {
+            Some((previous_start, previous_len, byte)) if *byte == base && *previous_start + *previous_len == start => {
+                *previous_len += len
+            }
+            _ => exceptions.push((start, len, base)),
+        }
+    }
+
+    let mut encoded = Vec::with_capacity(packed.len() + 4 * exceptions.len() + 8);
+    write_varint(&mut encoded, sequence.len() as u64);
+    write_varint(&mut encoded, exceptions.len() as u64);
+    let mut previous_end = 0;
+    for &(start, len, byte) in &exceptions {
+        write_varint(&mut encoded, (start - previous_end) as u64);
+        write_varint(&mut encoded, len as u64);
+        encoded.push(byte);
+        previous_end = start + len;
+    }
+    encoded.extend_from_slice(&packed);
+
+    Ok(encoded)
+}
+
+fn pack_chunk(chunk: &[u8], offset: usize, bases: &[u8; 4], packed: &mut [u8]) -> Result<Vec<(usize, usize, u8)>, CompressionError> {
+    let mut exceptions: Vec<(usize, usize, u8)> = Vec::new();
+    for (i, &base) in chunk.iter().enumerate() {
+        let base = base.to_ascii_uppercase();
+        if let Some(code) = bases.iter().position(|&b| b == base) {
+            packed[i / 4] |= (code as u8) << (6 - 2 * (i % 4));
+            continue;
+        }
+        if !is_exception(base) {
+            return Err(CompressionError::UnknownSequence((base as char).to_string()));
+        }
+        match exceptions.last_mut() {
+            Some((start, len, byte)) if *byte == base && *start + *len == offset + i => *len += 1,
+            _ => exceptions.push((offset + i, 1, base)),
+        }
+    }
+    Ok(exceptions)
+}
+
+pub fn decode(encoded: &[u8], mode: &Mode) -> Result<Vec<u8>, CompressionError> {
+    let mut input = encoded;
+    let len = read_length(&mut input)?;
+    let exception_count = read_length(&mut input)?;
+
+    let mut exceptions = Vec::with_capacity(exception_count.min(input.len()));
+    let mut previous_end = 0usize;
+    for _ in 0..exception_count {
+        let start = previous_end.saturating_add(read_length(&mut input)?);
+        let run = read_length(&mut input)?;
+        let (&byte, rest) = input.split_first().ok_or_else(|| truncated("exception table"))?;
+        input = rest;
+        previous_end = start.saturating_add(run);
+        if previous_end > len {
+            return Err(CompressionError::InvalidEncoding("exception outside of the sequence".to_string()));
+        }
+        exceptions.push((start, run, byte));
+    }
+
+    if input.len() != len.div_ceil(4) {
+        return Err(truncated("packed bases"));
+    }
+
+    let table = decoding_table(mode)?;
+    let mut decoded = Vec::with_capacity(len);
+    for &byte in input {
+        decoded.extend_from_slice(&table[byte as usize]);
+    }
+    decoded.truncate(len);
+
+    for (start, run, byte) in exceptions {
+        decoded[start..start + run].fill(byte);
+    }
+
+    Ok(decoded)
+}
+
+fn bases(mode: &Mode) -> Result<&'static [u8; 4], CompressionError> {
+    match mode {
+        Mode::DNA => Ok(DNA_BASES),
+        Mode::RNA => Ok(RNA_BASES),
+        Mode::Protein => Err(CompressionError::UnsupportedMode { codec: "2bit".to_string(), mode: mode.clone() }),
+    }
+}
+
+fn decoding_table(mode: &Mode) -> Result<&'static [[u8; 4]; 256], CompressionError> {
+    match mode {
+        Mode::DNA => Ok(&DNA_DECODING_TABLE),
+        Mode::RNA => Ok(&RNA_DECODING_TABLE),
+        Mode::Protein => Err(CompressionError::UnsupportedMode { codec: "2bit".to_string(), mode: mode.clone() }),
+    }
+}
+
+fn is_exception(base: u8) -> bool {
+    base == b'N' || is_ambiguity_code(base)
+}
+
+fn create_decoding_table(bases: &[u8; 4]) -> [[u8; 4]; 256] {
+    let mut table = [[0u8; 4]; 256];
+    for (byte, entry) in table.iter_mut().enumerate() {
+        for (i, base) in entry.iter_mut().enumerate() {
+            *base = bases[(byte >> (6 - 2 * i)) & 0b11];
+        }
+    }
+    table
+}
+
+fn read_length(input: &mut &[u8]) -> Result<usize, CompressionError> {
+    read_varint(input)
+        .map(|value| value as usize)
+        .ok_or_else(|| truncated("length"))
+}
+
+fn truncated(section: &str) -> CompressionError {
+    CompressionError::InvalidEncoding(format!("truncated {}", section))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_decoding_table() {
+        let table = create_decoding_table(DNA_BASES);
+        assert_eq!(&table[0b00_00_00_00], b"AAAA");
+        assert_eq!(&table[0b00_01_10_11], b"ACGT");
+        assert_eq!(&table[0b11_11_11_11], b"TTTT");
+
+        let table = create_decoding_table(RNA_BASES);
+        assert_eq!(&table[0b11_10_01_00], b"UGCA");
+    }
+
+    #[test]
+    fn test_dna_round_trip() {
+        let test_strings = ["", "A", "AC", "ACG", "ACGT", "ACGTA", "GATTACAGATTACA", "acgtn"];
+        for s in test_strings {
+            let encoded = encode(s.as_bytes(), &Mode::DNA).unwrap();
+            let decoded = decode(&encoded, &Mode::DNA).unwrap();
+            assert_eq!(decoded, s.to_uppercase().as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_rna_round_trip() {
+        let sequence = b"AUGGCUUAGNNNNCCAU";
+        let encoded = encode(sequence, &Mode::RNA).unwrap();
+        assert_eq!(decode(&encoded, &Mode::RNA).unwrap(), sequence);
+    }
+
+    #[test]
+    fn test_packs_four_bases_per_byte() {
+        let sequence = "ACGT".repeat(1000);
+        let encoded = encode(sequence.as_bytes(), &Mode::DNA).unwrap();
+        assert!(encoded.len() <= sequence.len() / 4 + 4);
+    }
+
+    #[test]
+    fn test_n_runs_are_stored_as_single_exceptions() {
+        let sequence = format!("{}{}{}", "ACGT".repeat(10), "N".repeat(500), "TTGA");
+        let encoded = encode(sequence.as_bytes(), &Mode::DNA).unwrap();
+
+        let mut input = encoded.as_slice();
+        read_varint(&mut input).unwrap();
+        assert_eq!(read_varint(&mut input), Some(1));
+        assert_eq!(decode(&encoded, &Mode::DNA).unwrap(), sequence.as_bytes());
+    }
+
+    #[test]
+    fn test_runs_spanning_parallel_chunks() {
+        let mut sequence = "ACGT".repeat(PARALLEL_CHUNK_BASES / 2).into_bytes();
+        sequence[PARALLEL_CHUNK_BASES - 3..PARALLEL_CHUNK_BASES + 5].fill(b'N');
+        let encoded = encode(&sequence, &Mode::DNA).unwrap();
+
+        let mut input = encoded.as_slice();
+        read_varint(&mut input).unwrap();
+        assert_eq!(read_varint(&mut input), Some(1));
+        assert_eq!(decode(&encoded, &Mode::DNA).unwrap(), sequence);
+    }
+
+    #[test]
+    fn test_ambiguity_codes_round_trip() {
+        let sequence = b"ACGTRYSWKMBDHVNNACGTRRRRYACGT";
+        let encoded = encode(sequence, &Mode::DNA).unwrap();
+        assert_eq!(decode(&encoded, &Mode::DNA).unwrap(), sequence);
+
+        let sequence = b"ACGURYSWKMBDHVNNACGU";
+        let encoded = encode(sequence, &Mode::RNA).unwrap();
+        assert_eq!(decode(&encoded, &Mode::RNA).unwrap(), sequence);
+    }
+
+    #[test]
+    fn test_unknown_base() {
+        assert!(matches!(encode(b"ACGU", &Mode::DNA), Err(CompressionError::UnknownSequence(_))));
+        assert!(matches!(encode(b"ACGT", &Mode::RNA), Err(CompressionError::UnknownSequence(_))));
+        assert!(matches!(encode(b"ACG-T", &Mode::DNA), Err(CompressionError::UnknownSequence(_))));
+        assert!(matches!(TwoBitCodec::new(&Mode::Protein), Err(CompressionError::UnsupportedMode { .. })));
+    }
+
+    #[test]
+    fn test_invalid_encoding() {
+        let encoded = encode(b"ACGTACGT", &Mode::DNA).unwrap();
+        let result = decode(&encoded[..encoded.len() - 1], &Mode::DNA);
+        assert!(matches!(result, Err(CompressionError::InvalidEncoding(_))));
+
+        let result = decode(&[4, 1, 3, 5, b'N', 0], &Mode::DNA);
+        assert!(matches!(result, Err(CompressionError::InvalidEncoding(_))));
+    }
+}
@@ -1,9 +1,17 @@
 use std::fmt;
+use std::path::PathBuf;
+
+use crate::container::SourceFormat;
+use crate::mode::Mode;
 
 #[derive(Debug)]
 pub enum CompressionError {
     UnknownSequence(String),
     UnknownCharacter(char),
+    InvalidEncoding(String),
+    UnknownCodec(String),
+    UnsupportedMode { codec: String, mode: Mode },
+    MalformedRecord(String),
 }
 
 impl fmt::Display for CompressionError {
@@ -11,6 +19,10 @@ impl fmt::Display for CompressionError {
         match self {
             CompressionError::UnknownSequence(seq) => write!(f, "Unknown sequence: {}", seq),
             CompressionError::UnknownCharacter(ch) => write!(f, "Unknown character: {}", ch),
+            CompressionError::InvalidEncoding(reason) => write!(f, "Invalid encoded data: {}", reason),
+            CompressionError::UnknownCodec(id) => write!(f, "Unknown codec: {}", id),
+            CompressionError::UnsupportedMode { codec, mode } => write!(f, "Codec {} does not support {:?} mode", codec, mode),
+            CompressionError::MalformedRecord(reason) => write!(f, "Malformed record: {}", reason),
         }
     }
 }
@@ -51,3 +63,167 @@ impl From<CompressionError> for FastaUnpackingError{
     }
 }
 
+#[derive(Debug)]
+pub enum ContainerError {
+    MissingHeader,
+    UnsupportedVersion(u8),
+    UnknownMode(u8),
+    UnknownFormat(u8),
+    UnknownLineEnding(u8),
+    LengthMismatch { expected: u64, found: u64 },
+    UnknownRecord(u8),
+    Truncated,
+    InvalidQualityBinning,
+    MissingIndex,
+    InvalidIndex,
+    MissingSummary,
+    InvalidSummary,
+    RecordChecksumMismatch(String),
+    RecordCountMismatch { expected: u64, found: u64 },
+    ContentChecksumMismatch,
+    FormatMismatch { expected: SourceFormat, found: SourceFormat },
+    ModeMismatch { expected: Mode, found: Mode },
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContainerError::MissingHeader => write!(f, "Missing container header: not a compact_sequence file"),
+            ContainerError::UnsupportedVersion(version) => write!(f, "Unsupported container format version: {}", version),
+            ContainerError::UnknownMode(id) => write!(f, "Unknown mode in container header: {}", id),
+            ContainerError::UnknownFormat(id) => write!(f, "Unknown source format in container header: {}", id),
+            ContainerError::UnknownLineEnding(id) => write!(f, "Unknown line ending in container header: {}", id),
+            ContainerError::LengthMismatch { expected, found } => {
+                write!(f, "Unpacked content is {} bytes long, the original was {}", found, expected)
+            }
+            ContainerError::UnknownRecord(id) => write!(f, "Unknown record type in compressed file: {}", id),
+            ContainerError::Truncated => write!(f, "Compressed file is truncated"),
+            ContainerError::InvalidQualityBinning => write!(f, "Invalid quality binning table in container header"),
+            ContainerError::MissingIndex => write!(f, "Compressed file has no sequence index"),
+            ContainerError::InvalidIndex => write!(f, "Sequence index in compressed file is corrupt"),
+            ContainerError::MissingSummary => write!(f, "Compressed file has no summary record"),
+            ContainerError::InvalidSummary => write!(f, "Summary record in compressed file is corrupt"),
+            ContainerError::RecordChecksumMismatch(record) => write!(f, "Checksum mismatch in {} of compressed file", record),
+            ContainerError::RecordCountMismatch { expected, found } => {
+                write!(f, "Compressed file should contain {} records, found {}", expected, found)
+            }
+            ContainerError::ContentChecksumMismatch => write!(f, "Checksum of unpacked content does not match the original"),
+            ContainerError::FormatMismatch { expected, found } => {
+                write!(f, "Source format mismatch: expected {} file, found {} file", expected, found)
+            }
+            ContainerError::ModeMismatch { expected, found } => {
+                write!(f, "Mode mismatch: expected {:?}, found {:?}", expected, found)
+            }
+            ContainerError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ContainerError {}
+
+impl From<std::io::Error> for ContainerError {
+    fn from(err: std::io::Error) -> Self {
+        ContainerError::Io(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum RegionError {
+    Invalid(String),
+    UnknownSequence(String),
+    OutOfRange { name: String, start: u64, len: u64 },
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegionError::Invalid(region) => write!(f, "Invalid region '{}', expected name:start-end", region),
+            RegionError::UnknownSequence(name) => write!(f, "Sequence '{}' not found in the index", name),
+            RegionError::OutOfRange { name, start, len } => {
+                write!(f, "Region start {} is past the end of '{}' ({} bases)", start, name, len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegionError {}
+
+#[derive(Debug)]
+pub enum VerifyError {
+    Mismatch { offset: u64, line: u64 },
+    Unsupported(String),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::Mismatch { offset, line } => {
+                write!(f, "Unpacked content differs from the original at byte offset {}, on line {}", offset, line)
+            }
+            VerifyError::Unsupported(reason) => write!(f, "Cannot verify: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+#[derive(Debug)]
+pub enum DirectoryError {
+    InvalidNaming(String),
+    OutputCollision { output: PathBuf, first: PathBuf, second: PathBuf },
+    OverwritesInput(PathBuf),
+    FilesFailed { failed: usize, total: usize },
+    UnsupportedFile(PathBuf),
+}
+
+impl fmt::Display for DirectoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DirectoryError::InvalidNaming(template) => {
+                write!(f, "Invalid naming scheme '{}', it needs {{stem}} or {{name}} and no path separators", template)
+            }
+            DirectoryError::OutputCollision { output, first, second } => {
+                write!(f, "{} and {} would both be written to {}", first.display(), second.display(), output.display())
+            }
+            DirectoryError::OverwritesInput(path) => write!(f, "Output would overwrite input file {}", path.display()),
+            DirectoryError::FilesFailed { failed, total } => write!(f, "{} of {} files failed", failed, total),
+            DirectoryError::UnsupportedFile(path) => write!(f, "No processor handles {}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for DirectoryError {}
+
+#[derive(Debug)]
+pub enum BundleError {
+    NotABundle,
+    UnsupportedVersion(u8),
+    CorruptToc,
+    InvalidMemberPath(String),
+    UnknownMember(String),
+    MemberChecksumMismatch(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BundleError::NotABundle => write!(f, "Not a compact_sequence directory bundle"),
+            BundleError::UnsupportedVersion(version) => write!(f, "Unsupported bundle format version: {}", version),
+            BundleError::CorruptToc => write!(f, "Table of contents of the bundle is corrupt or truncated"),
+            BundleError::InvalidMemberPath(path) => write!(f, "Invalid bundle member path '{}'", path),
+            BundleError::UnknownMember(path) => write!(f, "Bundle has no member '{}'", path),
+            BundleError::MemberChecksumMismatch(path) => write!(f, "Checksum mismatch in bundle member '{}'", path),
+            BundleError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl From<std::io::Error> for BundleError {
+    fn from(err: std::io::Error) -> Self {
+        BundleError::Io(err)
+    }
+}
@@ -1,9 +1,33 @@
+use crate::container::SourceFormat;
+
+const FASTA_EXTENSIONS: &[&str] = &["fasta", "fa", "fas", "fna", "faa"];
+const FASTQ_EXTENSIONS: &[&str] = &["fastq", "fq"];
+const TEXT_EXTENSIONS: &[&str] = &["txt"];
+
 pub fn is_fasta_extension(ext: &str) -> bool {
-    ["fasta", "fa", "fas", "fna"].contains(&ext)
+    FASTA_EXTENSIONS.contains(&ext)
+}
+
+pub fn is_fastq_extension(ext: &str) -> bool {
+    FASTQ_EXTENSIONS.contains(&ext)
 }
 
 pub fn is_text_extension(ext: &str) -> bool {
-    ["txt"].contains(&ext)
+    TEXT_EXTENSIONS.contains(&ext)
+}
+
+pub fn format_for_extension(ext: &str) -> Option<SourceFormat> {
+    match ext {
+        ext if is_fasta_extension(ext) => Some(SourceFormat::Fasta),
+        ext if is_fastq_extension(ext) => Some(SourceFormat::Fastq),
+        ext if is_text_extension(ext) => Some(SourceFormat::Text),
+        _ => None,
+    }
+}
+
+// Every extension some processor handles, the default filter for directories.
+pub fn supported_extensions() -> Vec<String> {
+    [FASTA_EXTENSIONS, FASTQ_EXTENSIONS, TEXT_EXTENSIONS].concat().iter().map(|ext| ext.to_string()).collect()
 }
 
 #[cfg(test)]
@@ -16,14 +40,32 @@ mod tests {
         assert!(is_fasta_extension("fa"));
         assert!(is_fasta_extension("fas"));
         assert!(is_fasta_extension("fna"));
+        assert!(is_fasta_extension("faa"));
         assert!(!is_fasta_extension("txt"));
         assert!(!is_fasta_extension("png"));
     }
 
+    #[test]
+    fn test_is_fastq_extension() {
+        assert!(is_fastq_extension("fastq"));
+        assert!(is_fastq_extension("fq"));
+        assert!(!is_fastq_extension("fasta"));
+        assert!(!is_fastq_extension("txt"));
+    }
+
     #[test]
     fn test_is_text_extension() {
         assert!(is_text_extension("txt"));
         assert!(!is_text_extension("fasta"));
         assert!(!is_text_extension("docx"));
     }
+
+    #[test]
+    fn test_format_for_extension() {
+        assert_eq!(format_for_extension("fna"), Some(SourceFormat::Fasta));
+        assert_eq!(format_for_extension("fq"), Some(SourceFormat::Fastq));
+        assert_eq!(format_for_extension("txt"), Some(SourceFormat::Text));
+        assert_eq!(format_for_extension("fai"), None);
+        assert_eq!(supported_extensions().len(), 8);
+    }
 }
@@ -0,0 +1,282 @@
+use std::io::{self, Read, Seek, Write};
+
+use crate::container::{read_index_offset, read_record_at, LineEnding, RecordKind};
+use crate::errors::ContainerError;
+use crate::line_layout::LineLayout;
+use crate::varint::{read_varint, write_varint};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexBlock {
+    pub offset: u64,
+    pub start: u64,
+    pub len: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub name: String,
+    pub len: u64,
+    pub original_offset: u64,
+    pub line_bases: u64,
+    pub line_width: u64,
+    // Set when lines other than the last differ in length, which faidx cannot describe.
+    pub irregular: bool,
+    pub blocks: Vec<IndexBlock>,
+}
+
+impl IndexEntry {
+    pub fn new(header: &str) -> Self {
+        let name = header.trim_start_matches('>').split_whitespace().next().unwrap_or("");
+        Self { name: name.to_string(), len: 0, original_offset: 0, line_bases: 0, line_width: 0, irregular: false, blocks: Vec::new() }
+    }
+
+    pub fn push_block(&mut self, offset: u64, len: u64) {
+        self.blocks.push(IndexBlock { offset, start: self.len, len });
+        self.len += len;
+    }
+
+    pub fn blocks_overlapping(&self, start: u64, end: u64) -> &[IndexBlock] {
+        let first = self.blocks.partition_point(|block| block.start + block.len <= start);
+        let last = self.blocks.partition_point(|block| block.start < end);
+        &self.blocks[first..last.max(first)]
+    }
+}
+
+// Written as the last data record of an indexed archive. Block starts are
+// implied by the running sum of block lengths, offsets are delta encoded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SequenceIndex {
+    pub entries: Vec<IndexEntry>,
+}
+
+impl SequenceIndex {
+    pub fn find(&self, name: &str) -> Option<&IndexEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn irregular_entry(&self) -> Option<&IndexEntry> {
+        self.entries.iter().find(|entry| entry.irregular)
+    }
+
+    pub fn load<R: Read + Seek>(reader: &mut R) -> Result<Self, ContainerError> {
+        let offset = read_index_offset(reader)?;
+        let record = read_record_at(reader, offset)?;
+        if record.kind != RecordKind::Index {
+            return Err(ContainerError::InvalidIndex);
+        }
+        Self::read_from(&record.payload)
+    }
+
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.entries.len() as u64);
+        let mut previous_offset = 0;
+        for entry in &self.entries {
+            write_varint(out, entry.name.len() as u64);
+            out.extend_from_slice(entry.name.as_bytes());
+            write_varint(out, entry.original_offset);
+            write_varint(out, entry.line_bases);
+            write_varint(out, entry.line_width);
+            write_varint(out, entry.irregular as u64);
+            write_varint(out, entry.blocks.len() as u64);
+            for block in &entry.blocks {
+                write_varint(out, block.offset - previous_offset);
+                write_varint(out, block.len);
+                previous_offset = block.offset;
+            }
+        }
+    }
+
+    pub fn read_from(input: &[u8]) -> Result<Self, ContainerError> {
+        let mut input = input;
+        let entry_count = read_value(&mut input)?;
+        let mut entries = Vec::new();
+        let mut previous_offset = 0u64;
+        for _ in 0..entry_count {
+            let name_len = read_value(&mut input)? as usize;
+            if name_len > input.len() {
+                return Err(ContainerError::InvalidIndex);
+            }
+            let (name, rest) = input.split_at(name_len);
+            input = rest;
+
+            let mut entry = IndexEntry::new(&String::from_utf8_lossy(name));
+            entry.original_offset = read_value(&mut input)?;
+            entry.line_bases = read_value(&mut input)?;
+            entry.line_width = read_value(&mut input)?;
+            entry.irregular = read_value(&mut input)? != 0;
+            for _ in 0..read_value(&mut input)? {
+                previous_offset = previous_offset.checked_add(read_value(&mut input)?).ok_or(ContainerError::InvalidIndex)?;
+                let len = read_value(&mut input)?;
+                if entry.len.checked_add(len).is_none() {
+                    return Err(ContainerError::InvalidIndex);
+                }
+                entry.push_block(previous_offset, len);
+            }
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    // Standard faidx columns: name, length, offset, line bases, line width.
+    pub fn write_fai<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for entry in &self.entries {
+            writeln!(writer, "{}\t{}\t{}\t{}\t{}", entry.name, entry.len, entry.original_offset, entry.line_bases, entry.line_width)?;
+        }
+        Ok(())
+    }
+
+    // One line per compressed block: name, first base, base count, archive offset.
+    pub fn write_block_offsets<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for entry in &self.entries {
+            for block in &entry.blocks {
+                writeln!(writer, "{}\t{}\t{}\t{}", entry.name, block.start, block.len, block.offset)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Builds the index while records are written (or rescanned) in order,
+// tracking where each sequence starts in the original FASTA.
+#[derive(Debug, Default)]
+pub struct IndexBuilder {
+    index: SequenceIndex,
+    original_offset: u64,
+    line_ending_len: u64,
+    short_line: bool,
+}
+
+impl IndexBuilder {
+    pub fn new(line_ending: LineEnding) -> Self {
+        Self { line_ending_len: line_ending.as_bytes().len() as u64, ..Self::default() }
+    }
+
+    pub fn add_header(&mut self, line: &str) {
+        self.original_offset += line.len() as u64 + self.line_ending_len;
+        let mut entry = IndexEntry::new(line);
+        entry.original_offset = self.original_offset;
+        self.index.entries.push(entry);
+        self.short_line = false;
+    }
+
+    // Every line has to be as long as the first one, except for a shorter last line.
+    pub fn add_block(&mut self, offset: u64, layout: &LineLayout) {
+        if let Some(entry) = self.index.entries.last_mut() {
+            if entry.blocks.is_empty() {
+                entry.line_bases = layout.first_line_len() as u64;
+                entry.line_width = entry.line_bases + self.line_ending_len;
+            }
+            for &(len, count) in layout.runs() {
+                let len = len as u64;
+                if self.short_line || len > entry.line_bases || (len < entry.line_bases && count > 1) {
+                    entry.irregular = true;
+                }
+                self.short_line |= len < entry.line_bases;
+            }
+            entry.push_block(offset, layout.total_len() as u64);
+        }
+        self.original_offset += layout.total_len() as u64 + layout.line_count() as u64 * self.line_ending_len;
+    }
+
+    pub fn finish(self) -> SequenceIndex {
+        self.index
+    }
+}
+
+fn read_value(input: &mut &[u8]) -> Result<u64, ContainerError> {
+    read_varint(input).ok_or(ContainerError::InvalidIndex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_index() -> SequenceIndex {
+        let mut builder = IndexBuilder::new(LineEnding::Lf);
+        builder.add_header(">chr1 first chromosome");
+        builder.add_block(30, &LineLayout::from_lines(&["A".repeat(50), "A".repeat(50)]));
+        builder.add_block(80, &LineLayout::from_lines(&["A".repeat(50), "A".repeat(50)]));
+        builder.add_block(130, &LineLayout::from_lines(&["A".repeat(20)]));
+        builder.add_header(">chr2");
+        builder.add_block(200, &LineLayout::from_lines(&["ACGTACG"]));
+        builder.add_header(">empty");
+        builder.finish()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let index = sample_index();
+        assert_eq!(index.entries[0].name, "chr1");
+        assert_eq!(index.entries[0].len, 220);
+
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes);
+        assert_eq!(SequenceIndex::read_from(&bytes).unwrap(), index);
+        assert!(matches!(SequenceIndex::read_from(&bytes[..bytes.len() - 1]), Err(ContainerError::InvalidIndex)));
+    }
+
+    #[test]
+    fn test_write_fai() {
+        let mut fai = Vec::new();
+        sample_index().write_fai(&mut fai).unwrap();
+        assert_eq!(String::from_utf8(fai).unwrap(), "chr1\t220\t23\t50\t51\nchr2\t7\t254\t7\t8\nempty\t0\t269\t0\t0\n");
+
+        let mut offsets = Vec::new();
+        sample_index().write_block_offsets(&mut offsets).unwrap();
+        assert_eq!(String::from_utf8(offsets).unwrap(), "chr1\t0\t100\t30\nchr1\t100\t100\t80\nchr1\t200\t20\t130\nchr2\t0\t7\t200\n");
+    }
+
+    #[test]
+    fn test_crlf_offsets() {
+        let mut builder = IndexBuilder::new(LineEnding::CrLf);
+        builder.add_header(">chr1");
+        builder.add_block(30, &LineLayout::from_lines(&["ACGT", "ACGT", "AC"]));
+        builder.add_header(">chr2");
+        builder.add_block(60, &LineLayout::from_lines(&["ACG"]));
+
+        let mut fai = Vec::new();
+        builder.finish().write_fai(&mut fai).unwrap();
+        assert_eq!(String::from_utf8(fai).unwrap(), "chr1\t10\t7\t4\t6\nchr2\t3\t30\t3\t5\n");
+    }
+
+    #[test]
+    fn test_irregular_lines() {
+        assert!(sample_index().irregular_entry().is_none());
+
+        let irregular_layouts: [&[&[&str]]; 4] = [
+            &[&["ACGT", "AC", "ACGT"]],
+            &[&["ACGT", "ACGTAC"]],
+            &[&["ACGT", "AC", "AC"]],
+            &[&["ACGT", "AC"], &["ACGT"]],
+        ];
+        for blocks in irregular_layouts {
+            let mut builder = IndexBuilder::new(LineEnding::Lf);
+            builder.add_header(">chr1");
+            for (i, lines) in blocks.iter().enumerate() {
+                builder.add_block(30 + i as u64 * 10, &LineLayout::from_lines(lines));
+            }
+            builder.add_header(">chr2");
+            builder.add_block(90, &LineLayout::from_lines(&["ACGT", "A"]));
+
+            let index = builder.finish();
+            assert_eq!(index.irregular_entry().map(|entry| entry.name.as_str()), Some("chr1"));
+            let mut bytes = Vec::new();
+            index.write_to(&mut bytes);
+            assert_eq!(SequenceIndex::read_from(&bytes).unwrap(), index);
+        }
+    }
+
+    #[test]
+    fn test_blocks_overlapping() {
+        let index = sample_index();
+        let chr1 = index.find("chr1").unwrap();
+        let offsets = |start, end| chr1.blocks_overlapping(start, end).iter().map(|block| block.offset).collect::<Vec<_>>();
+
+        assert_eq!(offsets(0, 10), vec![30]);
+        assert_eq!(offsets(99, 101), vec![30, 80]);
+        assert_eq!(offsets(100, 200), vec![80]);
+        assert_eq!(offsets(150, 220), vec![80, 130]);
+        assert!(offsets(220, 230).is_empty());
+        assert!(index.find("chr3").is_none());
+    }
+}
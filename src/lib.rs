@@ -1,365 +1,1477 @@
-use rayon::prelude::*;
-use std::fs::File;
-use std::io::{BufReader, BufRead, Write, BufWriter};
-
-pub mod encoders;
-pub mod errors;
-pub mod processors;
-pub mod mode;
-pub mod file_extensions;
-
-
-use mode::Mode;
-use encoders::Encoder;
-use errors::{CompressionError, FastaCompressionError, FastaUnpackingError};
-
-fn compress_string(input: &str, mode: &Mode) -> Result<String, CompressionError> {
-    let encoder = Encoder::new(mode);
-
-    let encoding_map = &encoder.encoding_map();
-
-    let input = input.to_uppercase();
-    let mut compressed = String::new();
-
-    for chunk in input.as_bytes().chunks(3) {
-        let key = String::from_utf8(chunk.to_vec()).unwrap();
-        if let Some(encoded_value) = encoding_map.get(&key) {
-            compressed.push_str(&encoded_value.to_string());
-        } else {
-            return Err(CompressionError::UnknownSequence(key));
-        }
-    }
-
-    Ok(compressed)
-}
-
-fn unpack_string(input: &str, mode: &Mode) -> Result<String, CompressionError> {
-    let encoder = Encoder::new(mode);
-
-    let decoding_map = &encoder.decoding_map();
-
-    let mut unpacked = String::new();
-    let mut previous_was_exclamation = false;
-
-    for ch in input.chars() {
-        if ch == '!' {
-            previous_was_exclamation = true;
-            continue;
-        }
-
-        let key = if previous_was_exclamation {
-            previous_was_exclamation = false;
-            format!("!{}", ch)
-        } else {
-            ch.to_string()
-        };
-
-        if let Some(decoded_value) = decoding_map.get(&key) {
-            unpacked.push_str(decoded_value);
-        } else {
-            return Err(CompressionError::UnknownCharacter(ch));
-        }
-    }
-
-    Ok(unpacked)
-}
-
-
-pub fn compress_to_file(input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
-    let input_file = File::open(input)?;
-    let reader = BufReader::new(input_file);
-
-    let lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
-    let compressed_lines: Vec<_> = lines.par_iter()
-        .map(|line| compress_string(line, &mode))
-        .collect::<Result<_, _>>()?;
-
-    let mut output_file = BufWriter::new(File::create(output_file_name)?);
-
-    for compressed_line in compressed_lines {
-        writeln!(output_file, "{}", compressed_line)?;
-    }
-
-    Ok(())
-}
-
-pub fn unpack_from_file(input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
-
-    let input_file = File::open(input)?;
-    let reader: Vec<_> = BufReader::new(input_file).lines().collect();
-    let output_file = File::create(output_file_name)?;
-    let mut writer = BufWriter::new(output_file);
-
-    reader.par_iter()
-          .map(|line| {
-              let line = line.as_ref().unwrap();
-              unpack_string(&line, &mode)
-          })
-          .collect::<Result<Vec<_>, _>>()?
-          .into_iter()
-          .try_for_each(|unpacked_line| writeln!(writer, "{}", unpacked_line))?;
-
-    Ok(())
-}
-
-fn compress_fasta_to_file(input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
-    let input_file = File::open(input)?;
-    let reader = BufReader::new(input_file);
-    let lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
-
-    let output_lines: Result<Vec<String>, FastaCompressionError> =
-    lines.par_iter().try_fold(
-        || Vec::new(),
-        |mut acc, line| -> Result<Vec<String>, FastaCompressionError> {
-            if line.starts_with('>') {
-                acc.push(line.clone());
-            } else {
-                let compressed_line = compress_string(&line, &mode)?;
-                acc.push(compressed_line);
-            }
-            Ok(acc)
-        },
-    ).try_reduce(
-        || Vec::new(),
-        |mut acc, x| -> Result<Vec<String>, FastaCompressionError> {
-            acc.extend(x);
-            Ok(acc)
-        },
-    );
-
-    let mut output_file = BufWriter::new(File::create(output_file_name)?);
-    output_lines?.iter().for_each(|line| writeln!(output_file, "{}", line).unwrap());
-
-    Ok(())
-}
-
-fn unpack_fasta_from_file(input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
-    let input_file = File::open(input)?;
-    let lines: Vec<String> = BufReader::new(input_file).lines().collect::<Result<_, _>>()?;
-
-    let output_lines: Result<Vec<String>, FastaUnpackingError> =
-        lines.par_iter().try_fold(
-            || Vec::new(),
-            |mut acc, line| -> Result<Vec<String>, FastaUnpackingError> {
-                if line.starts_with('>') {
-                    acc.push(line.clone());
-                } else {
-                    let unpacked_line = unpack_string(&line, &mode)?; 
-                    acc.push(unpacked_line);
-                }
-                Ok(acc)
-            },
-        ).try_reduce(
-            || Vec::new(),
-            |mut acc, x| -> Result<Vec<String>, FastaUnpackingError> {
-                acc.extend(x);
-                Ok(acc)
-            },
-        );
-
-    let mut output_file = BufWriter::new(File::create(output_file_name)?);
-    for line in output_lines? {
-        writeln!(output_file, "{}", line)?;
-    }
-
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    const DNA_TEST_MODE: Mode = Mode::DNA;
-    const RNA_TEST_MODE: Mode = Mode::RNA;
-    
-    #[test]
-    fn test_dna_compress_string() {
-        let test_strings = vec!["AAAA", "AC", "AAAACCCGTT", "AGGGGCCCCTTTTAA", ""];
-        for s in test_strings {
-            let compressed = compress_string(s, &DNA_TEST_MODE).unwrap();
-            let expected_len = (s.len() + 2) / 3;
-            assert_eq!(compressed.len(), expected_len);
-        }
-    }
-
-    #[test]
-    fn test_dna_unpack_string() {
-        let test_strings = vec!["A", "Aq1", "123", "5", ""];
-        for s in test_strings {
-            let unpacked = unpack_string(s, &DNA_TEST_MODE).unwrap();
-            assert!(unpacked.len() >= s.len());
-        }
-    }
-
-    #[test]
-    fn test_dna_compress_to_file() {
-        let input_strings = vec!["AAAA", "AC", "AAAACCCGTT", "AGGGGCCCCTTTTAA",""];
-        let input_file_name = "test_input_compress_dna.txt";
-        let mut input_file = File::create(input_file_name).unwrap();
-        for s in &input_strings {
-            writeln!(input_file, "{}", s).unwrap();
-        }
-
-        let output_file_name = "test_output_compress_dna.txt";
-        compress_to_file(input_file_name, output_file_name, &DNA_TEST_MODE).unwrap();
-
-        let output_file = File::open(output_file_name).unwrap();
-        let reader = BufReader::new(output_file);
-        let output_lines: Vec<_> = reader.lines().map(|line| line.unwrap()).collect();
-
-        for (input, output) in input_strings.iter().zip(output_lines.iter()) {
-            let expected_len = (input.len() + 2) / 3;
-            assert_eq!(output.len(), expected_len);
-        }
-
-        std::fs::remove_file(input_file_name).unwrap();
-        std::fs::remove_file(output_file_name).unwrap();
-    }
-
-    #[test]
-    fn test_dna_unpack_from_file() {
-        let input_strings = vec!["A4", "AC", "A4C3G1T2", "AG4C4T4A2"];
-        let input_file_name = "test_input_unpack_dna.txt";
-        let mut input_file = File::create(input_file_name).unwrap();
-        for s in &input_strings {
-            writeln!(input_file, "{}", s).unwrap();
-        }
-
-        let output_file_name = "test_output_unpack_dna.txt";
-        unpack_from_file(input_file_name, output_file_name, &DNA_TEST_MODE).unwrap();
-
-        let output_file = File::open(output_file_name).unwrap();
-        let reader = BufReader::new(output_file);
-        let output_lines: Vec<_> = reader.lines().map(|line| line.unwrap()).collect();
-
-        for (input, output) in input_strings.iter().zip(output_lines.iter()) {
-            assert!(output.len() >= input.len());
-        }
-
-        std::fs::remove_file(input_file_name).unwrap();
-        std::fs::remove_file(output_file_name).unwrap();
-    }
-
-    #[test]
-    fn test_rna_compress_string() {
-        let test_strings = vec!["AAAA", "AC", "AAANNNACCCGUU", "AGGNNNGGCCCCUUUAA", ""];
-        for s in test_strings {
-            let compressed = compress_string(s, &RNA_TEST_MODE).unwrap();
-            println!("{}, {}", compressed, s);
-            let expected_len = (s.len() + 2) / 3;
-            assert_eq!(compressed.len(), expected_len);
-        }
-    }
-
-    #[test]
-    fn test_rna_unpack_string() {
-        let test_strings = vec!["A", "Aq1", "123", "5", ""];
-        for s in test_strings {
-            let unpacked = unpack_string(s, &RNA_TEST_MODE).unwrap();
-            assert!(unpacked.len() >= s.len());
-        }
-    }
-
-    #[test]
-    fn test_rna_compress_to_file() {
-        let input_strings = vec!["AAAA", "AC", "AAAACCCGUU", "AGGNNNGGCCCCUUUUAA",""];
-        let input_file_name = "test_input_compress_rna.txt";
-        let mut input_file = File::create(input_file_name).unwrap();
-        for s in &input_strings {
-            writeln!(input_file, "{}", s).unwrap();
-        }
-
-        let output_file_name = "test_output_compress_rna.txt";
-        compress_to_file(input_file_name, output_file_name, &RNA_TEST_MODE).unwrap();
-
-        let output_file = File::open(output_file_name).unwrap();
-        let reader = BufReader::new(output_file);
-        let output_lines: Vec<_> = reader.lines().map(|line| line.unwrap()).collect();
-
-        for (input, output) in input_strings.iter().zip(output_lines.iter()) {
-            let expected_len = (input.len() + 2) / 3;
-            assert_eq!(output.len(), expected_len);
-        }
-
-        std::fs::remove_file(input_file_name).unwrap();
-        std::fs::remove_file(output_file_name).unwrap();
-    }
-
-    #[test]
-    fn test_rna_unpack_from_file() {
-        let input_strings = vec!["A4", "AC", "A4C3G1T2", "AG4C4T4A2"];
-        let input_file_name = "test_input_unpack_rna.txt";
-        let mut input_file = File::create(input_file_name).unwrap();
-        for s in &input_strings {
-            writeln!(input_file, "{}", s).unwrap();
-        }
-
-        let output_file_name = "test_output_unpack_rna.txt";
-        unpack_from_file(input_file_name, output_file_name, &RNA_TEST_MODE).unwrap();
-
-        let output_file = File::open(output_file_name).unwrap();
-        let reader = BufReader::new(output_file);
-        let output_lines: Vec<_> = reader.lines().map(|line| line.unwrap()).collect();
-
-        for (input, output) in input_strings.iter().zip(output_lines.iter()) {
-            assert!(output.len() >= input.len());
-        }
-
-        std::fs::remove_file(input_file_name).unwrap();
-        std::fs::remove_file(output_file_name).unwrap();
-    }
-    
-    #[test]
-    fn test_dna_compress_fasta_to_file() {
-        let input_strings = vec![(">desc1", "AAAA"), (">desc2", "AC"), (">desc3", "AAAACCCGTT"), (">desc4", "AGGGGCCCCTTTTAA")];
-        let input_file_name = "test_input_compress_dna.fasta";
-        let mut input_file = File::create(input_file_name).unwrap();
-        for (desc, seq) in &input_strings {
-            writeln!(input_file, "{}\n{}", desc, seq).unwrap();
-        }
-
-        let output_file_name = "test_output_compress_dna.fasta";
-        compress_fasta_to_file(input_file_name, output_file_name, &DNA_TEST_MODE).unwrap();
-
-        let output_file = File::open(output_file_name).unwrap();
-        let reader = BufReader::new(output_file);
-        let mut output_lines = reader.lines().map(|line| line.unwrap());
-
-        for (desc, seq) in &input_strings {
-            assert_eq!(output_lines.next().unwrap(), *desc);
-
-            let expected_seq_compressed = compress_string(seq, &DNA_TEST_MODE).unwrap();
-            assert_eq!(output_lines.next().unwrap(), expected_seq_compressed);
-        }
-
-        std::fs::remove_file(input_file_name).unwrap();
-        std::fs::remove_file(output_file_name).unwrap();
-    }
-
-    #[test]
-    fn test_rna_unpack_fasta_from_file() {
-        let input_strings = vec![(">desc1", "A4"), (">desc2", "AC"), (">desc3", "A4C3G1T2"), (">desc4", "AG4C4T4A2")];
-        let input_file_name = "test_input_unpack_rna.fasta";
-        let mut input_file = File::create(input_file_name).unwrap();
-        for (desc, seq) in &input_strings {
-            writeln!(input_file, "{}\n{}", desc, seq).unwrap();
-        }
-
-        let output_file_name = "test_output_unpack_rna.fasta";
-        unpack_fasta_from_file(input_file_name, output_file_name, &RNA_TEST_MODE).unwrap();
-
-        let output_file = File::open(output_file_name).unwrap();
-        let reader = BufReader::new(output_file);
-        let mut output_lines = reader.lines().map(|line| line.unwrap());
-
-        for (desc, seq) in &input_strings {
-            assert_eq!(output_lines.next().unwrap(), *desc);
-
-            let expected_seq_unpacked = unpack_string(seq, &RNA_TEST_MODE).unwrap();
-            assert_eq!(output_lines.next().unwrap(), expected_seq_unpacked);
-        }
-
-        std::fs::remove_file(input_file_name).unwrap();
-        std::fs::remove_file(output_file_name).unwrap();
-    }
-}
\ No newline at end of file
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufReader, BufRead, Read, Write, BufWriter};
+use std::error::Error;
+use std::path::Path;
+
+pub mod encoders;
+pub mod errors;
+pub mod processors;
+pub mod mode;
+pub mod file_extensions;
+pub mod container;
+pub mod codec;
+pub mod case_mask;
+pub mod quality;
+pub mod line_layout;
+pub mod index;
+pub mod region;
+pub mod checksum;
+pub mod verify;
+pub mod summary;
+pub mod stdio;
+pub mod bundle;
+mod batch;
+mod varint;
+
+
+use mode::Mode;
+use codec::{Codec, codec_for};
+use batch::{read_line_batch, read_record_batch, LineReader, LineWriter, BATCH_BYTES};
+use case_mask::CaseMask;
+use line_layout::{LineLayout, ReflowWriter};
+use quality::QualityBinning;
+use container::{read_record_at, ArchiveWriter, Header, LineEnding, Record, RecordKind, RecordReader, SourceFormat};
+use checksum::ChecksumWriter;
+use verify::CompareWriter;
+use summary::{ArchiveInfo, ArchiveSummary, SummaryBuilder};
+use stdio::{create_output, open_input, open_seekable, CountingReader};
+use errors::{CompressionError, ContainerError, FastaCompressionError, FastaUnpackingError, RegionError};
+use index::{IndexBuilder, SequenceIndex};
+use region::Region;
+
+const CHUNK_BASES: usize = 3 << 18;
+const FASTA_BLOCK_BASES: usize = 1 << 16;
+const EXTRACT_BATCH_BLOCKS: usize = 64;
+
+type ArchiveReader = RecordReader<BufReader<Box<dyn Read>>>;
+type SourceLines = LineReader<BufReader<CountingReader<Box<dyn Read>>>>;
+type OpenedArchive = (Header, Box<dyn Codec>, ArchiveReader);
+
+fn encode_sequence(sequence: &[u8], codec: &dyn Codec, sink: &mut Vec<u8>) -> Result<(), CompressionError> {
+    match codec.chunk_alignment() {
+        Some(alignment) if sequence.len() > CHUNK_BASES => {
+            let chunk_len = (CHUNK_BASES / alignment).max(1) * alignment;
+            let encoded_chunks = sequence.par_chunks(chunk_len)
+                .map(|chunk| {
+                    let mut encoded = Vec::new();
+                    codec.encode(chunk, &mut encoded)?;
+                    Ok(encoded)
+                })
+                .collect::<Result<Vec<_>, CompressionError>>()?;
+            for encoded in encoded_chunks {
+                sink.extend_from_slice(&encoded);
+            }
+            Ok(())
+        }
+        _ => codec.encode(sequence, sink),
+    }
+}
+
+fn sequence_record(input: &str, codec: &dyn Codec) -> Result<Record, CompressionError> {
+    let sequence = input.as_bytes();
+    let mask = CaseMask::from_sequence(sequence);
+    let mut payload = Vec::new();
+
+    if mask.is_empty() {
+        encode_sequence(sequence, codec, &mut payload)?;
+        return Ok(Record::new(RecordKind::Sequence, payload));
+    }
+
+    mask.write_to(&mut payload);
+    encode_sequence(&sequence.to_ascii_uppercase(), codec, &mut payload)?;
+    Ok(Record::new(RecordKind::MaskedSequence, payload))
+}
+
+fn wrapped_sequence_record(lines: &[String], codec: &dyn Codec) -> Result<Record, CompressionError> {
+    let sequence = lines.concat().into_bytes();
+    let mask = CaseMask::from_sequence(&sequence);
+    let mut payload = Vec::new();
+
+    LineLayout::from_lines(lines).write_to(&mut payload);
+    mask.write_to(&mut payload);
+    if mask.is_empty() {
+        encode_sequence(&sequence, codec, &mut payload)?;
+    } else {
+        encode_sequence(&sequence.to_ascii_uppercase(), codec, &mut payload)?;
+    }
+    Ok(Record::new(RecordKind::WrappedSequence, payload))
+}
+
+fn unpack_record(record: &Record, codec: &dyn Codec, line_ending: LineEnding) -> Result<Vec<u8>, CompressionError> {
+    let mut decoded = Vec::new();
+    match record.kind {
+        RecordKind::Raw => decoded.extend_from_slice(&record.payload),
+        RecordKind::Sequence => codec.decode(&record.payload, &mut decoded)?,
+        RecordKind::MaskedSequence => {
+            let mut payload = record.payload.as_slice();
+            let mask = CaseMask::read_from(&mut payload)?;
+            codec.decode(payload, &mut decoded)?;
+            mask.apply(&mut decoded)?;
+        }
+        RecordKind::WrappedSequence => {
+            let (layout, sequence) = decode_wrapped_sequence(&record.payload, codec)?;
+            decoded = layout.wrap(&sequence, line_ending)?;
+        }
+        RecordKind::Quality => {
+            return Err(CompressionError::InvalidEncoding("quality scores outside of a FASTQ record".to_string()))
+        }
+        RecordKind::Index | RecordKind::Footer | RecordKind::Summary => {
+            return Err(CompressionError::InvalidEncoding("unexpected metadata record".to_string()))
+        }
+    }
+    Ok(decoded)
+}
+
+// FASTQ records are unpacked four lines at a time so quality scores can be
+// bounded by the length of the sequence they belong to.
+fn unpack_fastq_record(records: &[Record], codec: &dyn Codec) -> Result<Vec<Vec<u8>>, CompressionError> {
+    let mut lines: Vec<Vec<u8>> = Vec::with_capacity(records.len());
+    for record in records {
+        let line = match record.kind {
+            RecordKind::Quality => quality::decode_qualities(&record.payload, lines.get(1).map_or(0, Vec::len))?,
+            _ => unpack_record(record, codec, LineEnding::Lf)?,
+        };
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn decode_wrapped_sequence(payload: &[u8], codec: &dyn Codec) -> Result<(LineLayout, Vec<u8>), CompressionError> {
+    let mut payload = payload;
+    let layout = LineLayout::read_from(&mut payload)?;
+    let mask = CaseMask::read_from(&mut payload)?;
+    let mut sequence = Vec::new();
+    codec.decode(payload, &mut sequence)?;
+    mask.apply(&mut sequence)?;
+    Ok((layout, sequence))
+}
+
+fn unpack_unwrapped_record(record: &Record, codec: &dyn Codec) -> Result<Vec<u8>, CompressionError> {
+    match record.kind {
+        RecordKind::WrappedSequence => Ok(decode_wrapped_sequence(&record.payload, codec)?.1),
+        _ => unpack_record(record, codec, LineEnding::Lf),
+    }
+}
+
+pub fn compress_to_file(input: &str, output_file_name: &str, mode: &Mode, codec: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let encoder = codec_for(codec, mode)?;
+    let mut lines = open_source(input)?;
+    // The first batch is read before the header so it can record the line ending.
+    let mut batch = read_line_batch(&mut lines, BATCH_BYTES, 1)?;
+    let header = Header::new(mode, codec, SourceFormat::Text).with_line_ending(lines.line_ending());
+
+    let mut output = ArchiveWriter::new(create_output(output_file_name)?, &header)?;
+    let mut content = LineWriter::new(ChecksumWriter::new(io::sink()), header.line_ending);
+    let mut summary = SummaryBuilder::default();
+    while !batch.is_empty() {
+        let compressed_records: Vec<_> = batch.par_iter()
+            .map(|line| sequence_record(line, encoder.as_ref()))
+            .collect::<Result<_, _>>()?;
+
+        for (line, compressed_record) in batch.iter().zip(compressed_records) {
+            content.write_line(line.as_bytes())?;
+            summary.add_sequence(line.as_bytes());
+            output.write_record(&compressed_record)?;
+        }
+        batch = read_line_batch(&mut lines, BATCH_BYTES, 1)?;
+    }
+
+    finish_archive(output, content, summary, &lines)?;
+    Ok(())
+}
+
+// Input is read through a counter so the original size is known even for stdin.
+fn open_source(input: &str) -> io::Result<SourceLines> {
+    Ok(LineReader::new(BufReader::new(CountingReader::new(open_input(input)?))))
+}
+
+// Every archive ends with a summary record followed by the footer. The content
+// is hashed the way it unpacks, ending with a line ending only if the original did.
+fn finish_archive<W: Write>(mut output: ArchiveWriter<W>, mut content: LineWriter<ChecksumWriter<io::Sink>>, summary: SummaryBuilder, lines: &SourceLines) -> io::Result<()> {
+    let original_len = lines.get_ref().get_ref().count();
+    content.finish_lines(content.bytes_written() < original_len)?;
+    output.footer_mut().content_checksum = content.get_ref().checksum();
+
+    let mut summary = summary.finish();
+    summary.original_len = original_len;
+    let mut payload = Vec::new();
+    summary.write_to(&mut payload);
+    let offset = output.write_record(&Record::new(RecordKind::Summary, payload))?;
+    output.footer_mut().summary_offset = Some(offset);
+    output.finish()?;
+    Ok(())
+}
+
+// Reads the header, summary and (for FASTA) index without decoding any sequence.
+pub fn archive_info(input: &str) -> Result<ArchiveInfo, Box<dyn Error>> {
+    let mut reader = open_seekable(input)?;
+    let header = Header::read_from(&mut reader)?;
+    let compressed_len = reader.get_ref().metadata()?.len();
+    let summary = ArchiveSummary::load(&mut reader)?;
+    let index = match SequenceIndex::load(&mut reader) {
+        Ok(index) => Some(index),
+        Err(ContainerError::MissingIndex) => None,
+        Err(err) => return Err(err.into()),
+    };
+    Ok(ArchiveInfo { header, compressed_len, summary, index })
+}
+
+pub fn unpack_from_file(input: &str, output_file_name: &str, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+    unpack_lines_from_file(input, output_file_name, SourceFormat::Text, mode)
+}
+
+fn unpack_lines_from_file(input: &str, output_file_name: &str, format: SourceFormat, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+    let (header, decoder, mut reader) = open_archive(input, format, mode)?;
+    unpack_lines(&mut reader, decoder.as_ref(), &header, create_output(output_file_name)?)?.flush()?;
+    Ok(())
+}
+
+fn open_archive(input: &str, format: SourceFormat, mode: Option<&Mode>) -> Result<OpenedArchive, Box<dyn Error>> {
+    open_archive_reader(input, open_input(input)?, format, mode)
+}
+
+fn open_archive_reader(input: &str, reader: Box<dyn Read>, format: SourceFormat, mode: Option<&Mode>) -> Result<OpenedArchive, Box<dyn Error>> {
+    let mut reader = BufReader::new(reader);
+    let header = Header::read_from(&mut reader)?;
+    header.validate(format, mode)?;
+    if let Some(binning) = &header.quality_binning {
+        eprintln!("Warning: {} is lossy, quality scores were binned ({}) during compression", input, binning);
+    }
+    let decoder = codec_for(&header.codec, &header.mode)?;
+    Ok((header, decoder, RecordReader::new(reader)))
+}
+
+fn unpack_lines<R: BufRead, W: Write>(reader: &mut RecordReader<R>, decoder: &dyn Codec, header: &Header, writer: W) -> Result<W, Box<dyn Error>> {
+    let mut writer = LineWriter::new(ChecksumWriter::new(writer), header.line_ending);
+    let group = if header.format == SourceFormat::Fastq { 4 } else { 1 };
+
+    loop {
+        let records = read_record_batch(reader, BATCH_BYTES, group)?;
+        if records.is_empty() {
+            break;
+        }
+        records.par_chunks(group)
+              .map(|records| match header.format {
+                  SourceFormat::Fastq => unpack_fastq_record(records, decoder),
+                  _ => records.iter().map(|record| unpack_record(record, decoder, header.line_ending)).collect(),
+              })
+              .collect::<Result<Vec<_>, _>>()?
+              .into_iter()
+              .flatten()
+              .try_for_each(|unpacked_line| writer.write_line(&unpacked_line))?;
+    }
+
+    Ok(finish_unpacked(reader, writer)?)
+}
+
+// The original length, which the summary record holds, tells whether the
+// content ended with a line ending, and has to match what was unpacked, as
+// does the checksum in the footer.
+fn finish_unpacked<R: Read, W: Write>(reader: &RecordReader<R>, mut writer: LineWriter<ChecksumWriter<W>>) -> Result<W, ContainerError> {
+    let summary = reader.summary().ok_or(ContainerError::MissingSummary)?;
+    let original_len = ArchiveSummary::read_from(&summary.payload)?.original_len;
+    writer.finish_lines(writer.bytes_written() < original_len)?;
+    if writer.bytes_written() != original_len {
+        return Err(ContainerError::LengthMismatch { expected: original_len, found: writer.bytes_written() });
+    }
+    reader.verify_content(writer.get_ref().checksum())?;
+    Ok(writer.into_inner().into_inner())
+}
+
+// Unpacks an archive of the given format to any writer, such as stdout.
+pub fn unpack_to_writer<W: Write>(input: &str, format: SourceFormat, mode: Option<&Mode>, line_width: Option<usize>, writer: W) -> Result<W, Box<dyn Error>> {
+    unpack_reader_to_writer(input, open_input(input)?, format, mode, line_width, writer)
+}
+
+// Same as `unpack_to_writer` for an archive read from any reader, such as
+// stdin or a member of a directory bundle. `name` is only used in messages.
+pub fn unpack_reader_to_writer<W: Write>(name: &str, reader: Box<dyn Read>, format: SourceFormat, mode: Option<&Mode>, line_width: Option<usize>, writer: W) -> Result<W, Box<dyn Error>> {
+    let (header, decoder, mut reader) = open_archive_reader(name, reader, format, mode)?;
+    match (format, line_width) {
+        (SourceFormat::Fasta, Some(width)) => reflow_fasta(&mut reader, decoder.as_ref(), &header, writer, width),
+        (SourceFormat::Fasta, None) => unpack_fasta_records(&mut reader, decoder.as_ref(), &header, writer),
+        (_, Some(_)) => Err("--line-width only applies to FASTA input".into()),
+        (_, None) => unpack_lines(&mut reader, decoder.as_ref(), &header, writer),
+    }
+}
+
+// Reads the header off a stream that cannot be opened again, such as stdin,
+// and returns it with a reader that replays it in front of the rest.
+pub fn read_archive_header(mut reader: Box<dyn Read>) -> Result<(Header, Box<dyn Read>), Box<dyn Error>> {
+    let header = Header::read_from(&mut reader)?;
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+    Ok((header, Box::new(io::Cursor::new(header_bytes).chain(reader))))
+}
+
+// Decodes an archive without writing the result anywhere. Record and content
+// checksums are always checked; given the original file the decoded bytes are
+// compared against it too, and the first difference is reported.
+pub fn verify_file(input: &str, original: Option<&str>, format: SourceFormat, mode: Option<&Mode>) -> Result<(), Box<dyn Error>> {
+    verify_reader(input, open_input(input)?, original, format, mode)
+}
+
+// Same as `verify_file` for an archive read from any reader. `name` is only
+// used in messages.
+pub fn verify_reader(name: &str, reader: Box<dyn Read>, original: Option<&str>, format: SourceFormat, mode: Option<&Mode>) -> Result<(), Box<dyn Error>> {
+    let (header, decoder, mut reader) = open_archive_reader(name, reader, format, mode)?;
+    let decoder = decoder.as_ref();
+    match original {
+        Some(original) => {
+            let original = CompareWriter::new(BufReader::new(open_input(original)?));
+            let original = match format {
+                SourceFormat::Fasta => unpack_fasta_records(&mut reader, decoder, &header, original)?,
+                _ => unpack_lines(&mut reader, decoder, &header, original)?,
+            };
+            original.finish()?;
+        }
+        None => {
+            match format {
+                SourceFormat::Fasta => unpack_fasta_records(&mut reader, decoder, &header, io::sink())?,
+                _ => unpack_lines(&mut reader, decoder, &header, io::sink())?,
+            };
+        }
+    }
+    Ok(())
+}
+
+// Header lines become their own block; runs of sequence lines are joined into
+// blocks of up to FASTA_BLOCK_BASES, split on line boundaries.
+fn fasta_blocks(lines: &[String]) -> Vec<&[String]> {
+    let mut blocks = Vec::new();
+    for run in lines.chunk_by(|a, b| !a.starts_with('>') && !b.starts_with('>')) {
+        if run[0].starts_with('>') {
+            blocks.push(run);
+            continue;
+        }
+        let mut block_start = 0;
+        let mut block_bases = 0;
+        for (i, line) in run.iter().enumerate() {
+            if block_bases > 0 && block_bases + line.len() > FASTA_BLOCK_BASES {
+                blocks.push(&run[block_start..i]);
+                block_start = i;
+                block_bases = 0;
+            }
+            block_bases += line.len();
+        }
+        blocks.push(&run[block_start..]);
+    }
+    blocks
+}
+
+fn compress_fasta_to_file(input: &str, output_file_name: &str, mode: &Mode, codec: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let encoder = codec_for(codec, mode)?;
+    let mut lines = open_source(input)?;
+    let mut batch = read_line_batch(&mut lines, BATCH_BYTES, 1)?;
+    let header = Header::new(mode, codec, SourceFormat::Fasta).with_line_ending(lines.line_ending());
+
+    let mut output = ArchiveWriter::new(create_output(output_file_name)?, &header)?;
+    let mut index = IndexBuilder::new(header.line_ending);
+    let mut content = LineWriter::new(ChecksumWriter::new(io::sink()), header.line_ending);
+    let mut summary = SummaryBuilder::default();
+    while !batch.is_empty() {
+        let blocks = fasta_blocks(&batch);
+        let output_records: Result<Vec<Record>, FastaCompressionError> =
+        blocks.par_iter().try_fold(
+            Vec::new,
+            |mut acc, block| -> Result<Vec<Record>, FastaCompressionError> {
+                if block[0].starts_with('>') {
+                    acc.push(Record::new(RecordKind::Raw, block[0].clone().into_bytes()));
+                } else {
+                    acc.push(wrapped_sequence_record(block, encoder.as_ref())?);
+                }
+                Ok(acc)
+            },
+        ).try_reduce(
+            Vec::new,
+            |mut acc, x| -> Result<Vec<Record>, FastaCompressionError> {
+                acc.extend(x);
+                Ok(acc)
+            },
+        );
+
+        for line in &batch {
+            content.write_line(line.as_bytes())?;
+            match line.starts_with('>') {
+                true => summary.start_sequence(),
+                false => summary.extend_sequence(line.as_bytes()),
+            }
+        }
+        for (block, record) in blocks.iter().zip(output_records?) {
+            let offset = output.write_record(&record)?;
+            match record.kind {
+                RecordKind::Raw => index.add_header(&block[0]),
+                _ => index.add_block(offset, &LineLayout::from_lines(block)),
+            }
+        }
+        batch = read_line_batch(&mut lines, BATCH_BYTES, 1)?;
+    }
+
+    let mut payload = Vec::new();
+    index.finish().write_to(&mut payload);
+    let index_offset = output.write_record(&Record::new(RecordKind::Index, payload))?;
+    output.footer_mut().index_offset = Some(index_offset);
+    finish_archive(output, content, summary, &lines)?;
+    Ok(())
+}
+
+// Rebuilds the sequence index by scanning every record of a compressed FASTA,
+// so it does not depend on the index stored at the end of the archive.
+pub fn scan_sequence_index(input: &str) -> Result<SequenceIndex, Box<dyn Error>> {
+    let mut reader = open_seekable(input)?;
+    let header = Header::read_from(&mut reader)?;
+    header.validate(SourceFormat::Fasta, None)?;
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+
+    let mut offset = header_bytes.len() as u64;
+    let mut index = IndexBuilder::new(header.line_ending);
+    let mut reader = RecordReader::new(reader);
+    while let Some(record) = reader.next_record()? {
+        match record.kind {
+            RecordKind::Raw => index.add_header(&String::from_utf8_lossy(&record.payload)),
+            RecordKind::WrappedSequence => index.add_block(offset, &LineLayout::read_from(&mut record.payload.as_slice())?),
+            kind if kind.is_metadata() => break,
+            _ => return Err(ContainerError::InvalidIndex.into()),
+        }
+        offset += record.encoded_len();
+    }
+    Ok(index.finish())
+}
+
+pub fn fasta_index_paths(archive: &str) -> (String, String) {
+    (format!("{}.fai", archive), format!("{}.cfi", archive))
+}
+
+// Writes the faidx compatible `.fai` and the `.cfi` block offsets next to the
+// archive. A `.fai` cannot describe sequences whose lines vary in length, so
+// none is written for those (and a stale one is removed).
+pub fn write_fasta_index_files(archive: &str, index: &SequenceIndex) -> Result<(), Box<dyn Error>> {
+    let (fai_path, block_offsets_path) = fasta_index_paths(archive);
+    match index.irregular_entry() {
+        Some(entry) => {
+            eprintln!("Warning: lines of '{}' vary in length, not writing {}", entry.name, fai_path);
+            if Path::new(&fai_path).exists() {
+                std::fs::remove_file(&fai_path)?;
+            }
+        }
+        None => {
+            let mut fai = BufWriter::new(File::create(fai_path)?);
+            index.write_fai(&mut fai)?;
+            fai.flush()?;
+        }
+    }
+    let mut block_offsets = BufWriter::new(File::create(block_offsets_path)?);
+    index.write_block_offsets(&mut block_offsets)?;
+    block_offsets.flush()?;
+    Ok(())
+}
+
+pub fn extract_region_from_file(input: &str, region: &str, output_file_name: &str, line_width: usize) -> Result<(), Box<dyn Error>> {
+    let mut reader = open_seekable(input)?;
+    let header = Header::read_from(&mut reader)?;
+    header.validate(SourceFormat::Fasta, None)?;
+    let decoder = codec_for(&header.codec, &header.mode)?;
+
+    let index = SequenceIndex::load(&mut reader)?;
+    let region = Region::parse(region, &index)?;
+    let entry = index.find(&region.name).ok_or_else(|| RegionError::UnknownSequence(region.name.clone()))?;
+    let (start, end) = region.bounds(entry.len)?;
+
+    let mut writer = ReflowWriter::new(create_output(output_file_name)?, line_width).with_line_ending(header.line_ending);
+    writer.write_line(format!(">{}", region).as_bytes())?;
+
+    for blocks in entry.blocks_overlapping(start, end).chunks(EXTRACT_BATCH_BLOCKS) {
+        let records = blocks.iter()
+            .map(|block| read_record_at(&mut reader, block.offset))
+            .collect::<Result<Vec<_>, _>>()?;
+        if records.iter().any(|record| record.kind != RecordKind::WrappedSequence) {
+            return Err(ContainerError::InvalidIndex.into());
+        }
+        let sequences: Vec<Vec<u8>> = records.par_iter()
+            .map(|record| unpack_unwrapped_record(record, decoder.as_ref()))
+            .collect::<Result<_, _>>()?;
+
+        for (block, sequence) in blocks.iter().zip(sequences) {
+            if sequence.len() as u64 != block.len {
+                return Err(ContainerError::InvalidIndex.into());
+            }
+            let from = start.saturating_sub(block.start) as usize;
+            let to = (end - block.start).min(block.len) as usize;
+            writer.write_sequence(&sequence[from..to])?;
+        }
+    }
+
+    writer.into_inner()?.flush()?;
+    Ok(())
+}
+
+fn unpack_fasta_from_file(input: &str, output_file_name: &str, mode: Option<&Mode>, line_width: Option<usize>) -> Result<(), Box<dyn std::error::Error>> {
+    let (header, decoder, mut reader) = open_archive(input, SourceFormat::Fasta, mode)?;
+    let output_file = create_output(output_file_name)?;
+
+    // Reflowed output has a layout of its own, so only the record checksums
+    // can be verified.
+    let mut output_file = match line_width {
+        Some(width) => reflow_fasta(&mut reader, decoder.as_ref(), &header, output_file, width)?,
+        None => unpack_fasta_records(&mut reader, decoder.as_ref(), &header, output_file)?,
+    };
+    output_file.flush()?;
+    Ok(())
+}
+
+fn unpack_fasta_records<R: BufRead, W: Write>(reader: &mut RecordReader<R>, decoder: &dyn Codec, header: &Header, writer: W) -> Result<W, Box<dyn std::error::Error>> {
+    let mut writer = LineWriter::new(ChecksumWriter::new(writer), header.line_ending);
+
+    loop {
+        let records = read_record_batch(reader, BATCH_BYTES, 1)?;
+        if records.is_empty() {
+            break;
+        }
+
+        let output_lines: Result<Vec<Vec<u8>>, FastaUnpackingError> =
+            records.par_iter().try_fold(
+                Vec::new,
+                |mut acc, record| -> Result<Vec<Vec<u8>>, FastaUnpackingError> {
+                    acc.push(unpack_record(record, decoder, header.line_ending)?);
+                    Ok(acc)
+                },
+            ).try_reduce(
+                Vec::new,
+                |mut acc, x| -> Result<Vec<Vec<u8>>, FastaUnpackingError> {
+                    acc.extend(x);
+                    Ok(acc)
+                },
+            );
+
+        for line in output_lines? {
+            writer.write_line(&line)?;
+        }
+    }
+
+    Ok(finish_unpacked(reader, writer)?)
+}
+
+fn reflow_fasta<R: BufRead, W: Write>(reader: &mut RecordReader<R>, decoder: &dyn Codec, header: &Header, writer: W, width: usize) -> Result<W, Box<dyn std::error::Error>> {
+    let mut writer = ReflowWriter::new(writer, width).with_line_ending(header.line_ending);
+
+    loop {
+        let records = read_record_batch(reader, BATCH_BYTES, 1)?;
+        if records.is_empty() {
+            break;
+        }
+
+        let unpacked: Vec<Vec<u8>> = records.par_iter()
+            .map(|record| unpack_unwrapped_record(record, decoder))
+            .collect::<Result<_, _>>()?;
+        for (record, unpacked) in records.iter().zip(unpacked) {
+            match record.kind {
+                RecordKind::Raw => writer.write_line(&unpacked)?,
+                _ => writer.write_sequence(&unpacked)?,
+            }
+        }
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+fn fastq_records(index: usize, lines: &[String], codec: &dyn Codec, quality_binning: Option<&QualityBinning>) -> Result<Vec<Record>, CompressionError> {
+    if lines.len() != 4 {
+        return Err(CompressionError::MalformedRecord(format!("FASTQ record {} is truncated, expected four lines", index + 1)));
+    }
+    let (header, sequence, separator, qualities) = (&lines[0], &lines[1], &lines[2], &lines[3]);
+    if !header.starts_with('@') {
+        return Err(CompressionError::MalformedRecord(format!("FASTQ record {} does not start with '@'", index + 1)));
+    }
+    if !separator.starts_with('+') {
+        return Err(CompressionError::MalformedRecord(format!("FASTQ record {} is missing the '+' separator", index + 1)));
+    }
+    if qualities.len() != sequence.len() {
+        return Err(CompressionError::MalformedRecord(format!("FASTQ record {} has {} quality scores for {} bases", index + 1, qualities.len(), sequence.len())));
+    }
+
+    Ok(vec![
+        Record::new(RecordKind::Raw, header.clone().into_bytes()),
+        sequence_record(sequence, codec)?,
+        Record::new(RecordKind::Raw, separator.clone().into_bytes()),
+        quality_record(qualities, quality_binning)?,
+    ])
+}
+
+fn quality_record(qualities: &str, quality_binning: Option<&QualityBinning>) -> Result<Record, CompressionError> {
+    let payload = match quality_binning {
+        Some(binning) => {
+            let mut binned = qualities.as_bytes().to_vec();
+            binning.apply(&mut binned);
+            quality::encode_qualities(&binned)?
+        }
+        None => quality::encode_qualities(qualities.as_bytes())?,
+    };
+    Ok(Record::new(RecordKind::Quality, payload))
+}
+
+fn compress_fastq_to_file(input: &str, output_file_name: &str, mode: &Mode, codec: &str, quality_binning: Option<&QualityBinning>) -> Result<(), Box<dyn std::error::Error>> {
+    let encoder = codec_for(codec, mode)?;
+    let mut lines = open_source(input)?;
+    let mut batch = read_line_batch(&mut lines, BATCH_BYTES, 4)?;
+    let header = Header::new(mode, codec, SourceFormat::Fastq)
+        .with_quality_binning(quality_binning)
+        .with_line_ending(lines.line_ending());
+
+    let mut output = ArchiveWriter::new(create_output(output_file_name)?, &header)?;
+    let mut content = LineWriter::new(ChecksumWriter::new(io::sink()), header.line_ending);
+    let mut summary = SummaryBuilder::default();
+    let mut first_record = 0;
+    while !batch.is_empty() {
+        let records: Vec<Vec<Record>> = batch.par_chunks(4)
+            .enumerate()
+            .map(|(index, lines)| fastq_records(first_record + index, lines, encoder.as_ref(), quality_binning))
+            .collect::<Result<_, _>>()?;
+        first_record += records.len();
+
+        for (i, line) in batch.iter().enumerate() {
+            match quality_binning {
+                Some(binning) if i % 4 == 3 => {
+                    let mut binned = line.clone().into_bytes();
+                    binning.apply(&mut binned);
+                    content.write_line(&binned)?;
+                }
+                _ => content.write_line(line.as_bytes())?,
+            }
+            if i % 4 == 1 {
+                summary.add_sequence(line.as_bytes());
+            }
+        }
+        for record in records.iter().flatten() {
+            output.write_record(record)?;
+        }
+        batch = read_line_batch(&mut lines, BATCH_BYTES, 4)?;
+    }
+
+    finish_archive(output, content, summary, &lines)?;
+    Ok(())
+}
+
+fn unpack_fastq_from_file(input: &str, output_file_name: &str, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+    unpack_lines_from_file(input, output_file_name, SourceFormat::Fastq, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::Footer;
+    use crate::encoders::AsciiCodec;
+
+    const DNA_TEST_MODE: Mode = Mode::DNA;
+    const RNA_TEST_MODE: Mode = Mode::RNA;
+
+    // Hand-built archives need a summary holding the length of what they
+    // unpack to and a footer holding its checksum.
+    pub(crate) fn write_test_archive(file_name: &str, header: &Header, records: &[Record]) {
+        let codec = codec_for(&header.codec, &header.mode).unwrap();
+        let mut content = LineWriter::new(ChecksumWriter::new(io::sink()), header.line_ending);
+        for record in records {
+            content.write_line(&unpack_record(record, codec.as_ref(), header.line_ending).unwrap()).unwrap();
+        }
+        content.finish_lines(true).unwrap();
+
+        let mut archive = ArchiveWriter::new(File::create(file_name).unwrap(), header).unwrap();
+        for record in records {
+            archive.write_record(record).unwrap();
+        }
+        let mut summary = ArchiveSummary::default();
+        summary.original_len = content.bytes_written();
+        let mut payload = Vec::new();
+        summary.write_to(&mut payload);
+        archive.write_record(&Record::new(RecordKind::Summary, payload)).unwrap();
+        archive.footer_mut().content_checksum = content.get_ref().checksum();
+        archive.finish().unwrap();
+    }
+    
+    #[test]
+    fn test_dna_compress_to_file() {
+        let input_strings = vec!["AAAA", "AC", "AAAACCCGTT", "AGGGGCCCCTTTTAA",""];
+        let input_file_name = "test_input_compress_dna.txt";
+        let mut input_file = File::create(input_file_name).unwrap();
+        for s in &input_strings {
+            writeln!(input_file, "{}", s).unwrap();
+        }
+
+        let output_file_name = "test_output_compress_dna.txt";
+        compress_to_file(input_file_name, output_file_name, &DNA_TEST_MODE, "ascii").unwrap();
+
+        let output_file = File::open(output_file_name).unwrap();
+        let mut reader = BufReader::new(output_file);
+        let header = Header::read_from(&mut reader).unwrap();
+        assert_eq!(header.format, SourceFormat::Text);
+        let mut records = Record::read_all(&mut reader).unwrap();
+        assert_eq!(records.pop().unwrap().kind, RecordKind::Footer);
+        assert_eq!(records.pop().unwrap().kind, RecordKind::Summary);
+        assert_eq!(records.len(), input_strings.len());
+
+        for (input, record) in input_strings.iter().zip(records.iter()) {
+            let expected_len = input.len().div_ceil(3);
+            assert_eq!(record.kind, RecordKind::Sequence);
+            assert_eq!(record.payload.len(), expected_len);
+        }
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_dna_unpack_from_file() {
+        let input_strings = ["A4", "AC", "A4C3G1T2", "AG4C4T4A2"];
+        let input_file_name = "test_input_unpack_dna.txt";
+        let records: Vec<_> = input_strings.iter().map(|s| Record::new(RecordKind::Sequence, s.as_bytes().to_vec())).collect();
+        write_test_archive(input_file_name, &Header::new(&DNA_TEST_MODE, "ascii", SourceFormat::Text), &records);
+
+        let output_file_name = "test_output_unpack_dna.txt";
+        unpack_from_file(input_file_name, output_file_name, Some(&DNA_TEST_MODE)).unwrap();
+
+        let output_file = File::open(output_file_name).unwrap();
+        let reader = BufReader::new(output_file);
+        let output_lines: Vec<_> = reader.lines().map(|line| line.unwrap()).collect();
+
+        for (input, output) in input_strings.iter().zip(output_lines.iter()) {
+            assert!(output.len() >= input.len());
+        }
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_rna_compress_to_file() {
+        let input_strings = vec!["AAAA", "AC", "AAAACCCGUU", "AGGNNNGGCCCCUUUUAA",""];
+        let input_file_name = "test_input_compress_rna.txt";
+        let mut input_file = File::create(input_file_name).unwrap();
+        for s in &input_strings {
+            writeln!(input_file, "{}", s).unwrap();
+        }
+
+        let output_file_name = "test_output_compress_rna.txt";
+        compress_to_file(input_file_name, output_file_name, &RNA_TEST_MODE, "ascii").unwrap();
+
+        let output_file = File::open(output_file_name).unwrap();
+        let mut reader = BufReader::new(output_file);
+        let header = Header::read_from(&mut reader).unwrap();
+        assert_eq!(header.format, SourceFormat::Text);
+        let mut records = Record::read_all(&mut reader).unwrap();
+        assert_eq!(records.pop().unwrap().kind, RecordKind::Footer);
+        assert_eq!(records.pop().unwrap().kind, RecordKind::Summary);
+        assert_eq!(records.len(), input_strings.len());
+
+        for (input, record) in input_strings.iter().zip(records.iter()) {
+            let expected_len = input.len().div_ceil(3);
+            assert_eq!(record.kind, RecordKind::Sequence);
+            assert_eq!(record.payload.len(), expected_len);
+        }
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_rna_unpack_from_file() {
+        let input_strings = ["A4", "AC", "A4C3G1T2", "AG4C4T4A2"];
+        let input_file_name = "test_input_unpack_rna.txt";
+        let records: Vec<_> = input_strings.iter().map(|s| Record::new(RecordKind::Sequence, s.as_bytes().to_vec())).collect();
+        write_test_archive(input_file_name, &Header::new(&RNA_TEST_MODE, "ascii", SourceFormat::Text), &records);
+
+        let output_file_name = "test_output_unpack_rna.txt";
+        unpack_from_file(input_file_name, output_file_name, Some(&RNA_TEST_MODE)).unwrap();
+
+        let output_file = File::open(output_file_name).unwrap();
+        let reader = BufReader::new(output_file);
+        let output_lines: Vec<_> = reader.lines().map(|line| line.unwrap()).collect();
+
+        for (input, output) in input_strings.iter().zip(output_lines.iter()) {
+            assert!(output.len() >= input.len());
+        }
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+    
+    #[test]
+    fn test_dna_compress_fasta_to_file() {
+        let input_strings = vec![(">desc1", "AAAA"), (">desc2", "AC"), (">desc3", "AAAACCCGTT"), (">desc4", "AGGGGCCCCTTTTAA")];
+        let input_file_name = "test_input_compress_dna.fasta";
+        let mut input_file = File::create(input_file_name).unwrap();
+        for (desc, seq) in &input_strings {
+            writeln!(input_file, "{}\n{}", desc, seq).unwrap();
+        }
+
+        let output_file_name = "test_output_compress_dna.fasta";
+        compress_fasta_to_file(input_file_name, output_file_name, &DNA_TEST_MODE, "ascii").unwrap();
+
+        let output_file = File::open(output_file_name).unwrap();
+        let mut reader = BufReader::new(output_file);
+        let header = Header::read_from(&mut reader).unwrap();
+        assert_eq!(header.format, SourceFormat::Fasta);
+        let mut records = Record::read_all(&mut reader).unwrap().into_iter();
+
+        for (desc, seq) in &input_strings {
+            assert_eq!(records.next().unwrap(), Record::new(RecordKind::Raw, desc.as_bytes().to_vec()));
+
+            let expected_seq_compressed = wrapped_sequence_record(&[seq.to_string()], &AsciiCodec::new(&DNA_TEST_MODE).unwrap()).unwrap();
+            assert_eq!(records.next().unwrap(), expected_seq_compressed);
+        }
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_rna_unpack_fasta_from_file() {
+        let input_strings = vec![(">desc1", "A4"), (">desc2", "AC"), (">desc3", "A4C3G1T2"), (">desc4", "AG4C4T4A2")];
+        let input_file_name = "test_input_unpack_rna.fasta";
+        let records: Vec<_> = input_strings.iter()
+            .flat_map(|(desc, seq)| [Record::new(RecordKind::Raw, desc.as_bytes().to_vec()), Record::new(RecordKind::Sequence, seq.as_bytes().to_vec())])
+            .collect();
+        write_test_archive(input_file_name, &Header::new(&RNA_TEST_MODE, "ascii", SourceFormat::Fasta), &records);
+
+        let output_file_name = "test_output_unpack_rna.fasta";
+        unpack_fasta_from_file(input_file_name, output_file_name, Some(&RNA_TEST_MODE), None).unwrap();
+
+        let output_file = File::open(output_file_name).unwrap();
+        let reader = BufReader::new(output_file);
+        let mut output_lines = reader.lines().map(|line| line.unwrap());
+
+        for (desc, seq) in &input_strings {
+            assert_eq!(output_lines.next().unwrap(), *desc);
+
+            let mut expected_seq_unpacked = Vec::new();
+            AsciiCodec::new(&RNA_TEST_MODE).unwrap().decode(seq.as_bytes(), &mut expected_seq_unpacked).unwrap();
+            assert_eq!(output_lines.next().unwrap().as_bytes(), expected_seq_unpacked);
+        }
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_unpack_rejects_missing_header() {
+        let input_file_name = "test_input_unpack_missing_header.txt";
+        let mut input_file = File::create(input_file_name).unwrap();
+        writeln!(input_file, "A4").unwrap();
+
+        let output_file_name = "test_output_unpack_missing_header.txt";
+        let err = unpack_from_file(input_file_name, output_file_name, Some(&DNA_TEST_MODE)).unwrap_err();
+        assert!(matches!(err.downcast_ref::<ContainerError>(), Some(ContainerError::MissingHeader)));
+
+        std::fs::remove_file(input_file_name).unwrap();
+        let _ = std::fs::remove_file(output_file_name);
+    }
+
+    #[test]
+    fn test_unpack_rejects_mismatched_header() {
+        let input_file_name = "test_input_unpack_mismatch.fasta";
+        let mut input_file = File::create(input_file_name).unwrap();
+        writeln!(input_file, ">desc1\nAAAACCCGTT").unwrap();
+
+        let compressed_file_name = "test_compressed_unpack_mismatch.fasta";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii").unwrap();
+
+        let output_file_name = "test_output_unpack_mismatch.txt";
+        let err = unpack_from_file(compressed_file_name, output_file_name, Some(&DNA_TEST_MODE)).unwrap_err();
+        assert!(matches!(err.downcast_ref::<ContainerError>(), Some(ContainerError::FormatMismatch { .. })));
+
+        let err = unpack_fasta_from_file(compressed_file_name, output_file_name, Some(&RNA_TEST_MODE), None).unwrap_err();
+        assert!(matches!(err.downcast_ref::<ContainerError>(), Some(ContainerError::ModeMismatch { .. })));
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        let _ = std::fs::remove_file(output_file_name);
+    }
+
+    #[test]
+    fn test_unpack_checks_original_length() {
+        let input_file_name = "test_input_unpack_length.txt";
+        let mut input_file = File::create(input_file_name).unwrap();
+        Header::new(&DNA_TEST_MODE, "ascii", SourceFormat::Text).write_to(&mut input_file).unwrap();
+        Record::new(RecordKind::Raw, b"ACGT".to_vec()).write_to(&mut input_file).unwrap();
+        let mut summary = ArchiveSummary::default();
+        summary.original_len = 6;
+        let mut payload = Vec::new();
+        summary.write_to(&mut payload);
+        Record::new(RecordKind::Summary, payload).write_to(&mut input_file).unwrap();
+        let footer = Footer { record_count: 2, ..Footer::default() };
+        footer.to_record().write_to(&mut input_file).unwrap();
+
+        let output_file_name = "test_output_unpack_length.txt";
+        let err = unpack_from_file(input_file_name, output_file_name, Some(&DNA_TEST_MODE)).unwrap_err();
+        assert!(matches!(err.downcast_ref::<ContainerError>(), Some(ContainerError::LengthMismatch { expected: 6, found: 5 })));
+
+        std::fs::remove_file(input_file_name).unwrap();
+        let _ = std::fs::remove_file(output_file_name);
+    }
+
+    #[test]
+    fn test_unpack_detects_mode_from_header() {
+        let input_file_name = "test_input_detect_mode.txt";
+        let mut input_file = File::create(input_file_name).unwrap();
+        writeln!(input_file, "AUGGCUNNAU").unwrap();
+
+        let compressed_file_name = "test_compressed_detect_mode.txt";
+        compress_to_file(input_file_name, compressed_file_name, &RNA_TEST_MODE, "ascii").unwrap();
+
+        let output_file_name = "test_output_detect_mode.txt";
+        unpack_from_file(compressed_file_name, output_file_name, None).unwrap();
+        assert_eq!(std::fs::read_to_string(output_file_name).unwrap(), "AUGGCUNNAU\n");
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_two_bit_compress_and_unpack_file() {
+        let input = "ACGTNNNNACGTAC\nGATTACA\n\nNNNN\n";
+        let input_file_name = "test_input_two_bit.txt";
+        std::fs::write(input_file_name, input).unwrap();
+
+        let compressed_file_name = "test_compressed_two_bit.txt";
+        compress_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "2bit").unwrap();
+
+        let mut reader = BufReader::new(File::open(compressed_file_name).unwrap());
+        assert_eq!(Header::read_from(&mut reader).unwrap().codec, "2bit");
+
+        let output_file_name = "test_output_two_bit.txt";
+        unpack_from_file(compressed_file_name, output_file_name, None).unwrap();
+        assert_eq!(std::fs::read_to_string(output_file_name).unwrap(), input);
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_two_bit_compress_and_unpack_fasta() {
+        let input = ">desc1\nACGUNNACGU\nAC\n>desc2\nGAUUACA\n";
+        let input_file_name = "test_input_two_bit.fasta";
+        std::fs::write(input_file_name, input).unwrap();
+
+        let compressed_file_name = "test_compressed_two_bit.fasta";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &RNA_TEST_MODE, "2bit").unwrap();
+
+        let output_file_name = "test_output_two_bit.fasta";
+        unpack_fasta_from_file(compressed_file_name, output_file_name, None, None).unwrap();
+        assert_eq!(std::fs::read_to_string(output_file_name).unwrap(), input);
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_soft_masking_round_trip() {
+        for codec in ["ascii", "2bit"] {
+            let input = ">chr1 soft-masked\nACGTacgtnnNNACGT\nacgtac\n>chr2\nGATTACA\n";
+            let input_file_name = format!("test_input_soft_mask_{}.fasta", codec);
+            std::fs::write(&input_file_name, input).unwrap();
+
+            let compressed_file_name = format!("test_compressed_soft_mask_{}.fasta", codec);
+            compress_fasta_to_file(&input_file_name, &compressed_file_name, &DNA_TEST_MODE, codec).unwrap();
+
+            let mut reader = BufReader::new(File::open(&compressed_file_name).unwrap());
+            Header::read_from(&mut reader).unwrap();
+            let kinds: Vec<_> = Record::read_all(&mut reader).unwrap().into_iter()
+                .map(|record| record.kind)
+                .filter(|kind| !kind.is_metadata())
+                .collect();
+            assert_eq!(kinds, vec![
+                RecordKind::Raw, RecordKind::WrappedSequence, RecordKind::Raw, RecordKind::WrappedSequence,
+            ]);
+
+            let output_file_name = format!("test_output_soft_mask_{}.fasta", codec);
+            unpack_fasta_from_file(&compressed_file_name, &output_file_name, None, None).unwrap();
+            assert_eq!(std::fs::read_to_string(&output_file_name).unwrap(), input);
+
+            std::fs::remove_file(input_file_name).unwrap();
+            std::fs::remove_file(compressed_file_name).unwrap();
+            std::fs::remove_file(output_file_name).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_protein_fasta_round_trip() {
+        let input = format!(
+            ">sp|P69905|HBA_HUMAN\n{}DLSHGSAQVKGHGKKVADALTNAVAHVDDMPNALSALSDLHAHKL*\n>partial\nmkvXBZUO\n",
+            "MVLSPADKTNVKAAWGKVGAHAGEYGAEALERMFLSFPTTKTYFPHF\n".repeat(10),
+        );
+        let input_file_name = "test_input_protein.faa";
+        std::fs::write(input_file_name, &input).unwrap();
+
+        let compressed_file_name = "test_compressed_protein.faa";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &Mode::Protein, "protein").unwrap();
+        assert!(std::fs::metadata(compressed_file_name).unwrap().len() < input.len() as u64);
+
+        let output_file_name = "test_output_protein.faa";
+        unpack_fasta_from_file(compressed_file_name, output_file_name, None, None).unwrap();
+        assert_eq!(std::fs::read_to_string(output_file_name).unwrap(), input);
+
+        let err = compress_fasta_to_file(input_file_name, compressed_file_name, &Mode::Protein, "ascii").unwrap_err();
+        assert!(matches!(err.downcast_ref::<CompressionError>(), Some(CompressionError::UnsupportedMode { .. })));
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_fastq_round_trip() {
+        for codec in ["ascii", "2bit"] {
+            let input = "@read1 length=12\nACGTNNacgtAC\n+\nIIIIII#####F\n@read2\nGATTACA\n+read2\nFFFF:FF\n";
+            let input_file_name = format!("test_input_{}.fastq", codec);
+            std::fs::write(&input_file_name, input).unwrap();
+
+            let compressed_file_name = format!("test_compressed_{}.fastq", codec);
+            compress_fastq_to_file(&input_file_name, &compressed_file_name, &DNA_TEST_MODE, codec, None).unwrap();
+
+            let mut reader = BufReader::new(File::open(&compressed_file_name).unwrap());
+            assert_eq!(Header::read_from(&mut reader).unwrap().format, SourceFormat::Fastq);
+            let kinds: Vec<_> = Record::read_all(&mut reader).unwrap().into_iter()
+                .map(|record| record.kind)
+                .filter(|kind| !kind.is_metadata())
+                .collect();
+            assert_eq!(kinds, vec![
+                RecordKind::Raw, RecordKind::MaskedSequence, RecordKind::Raw, RecordKind::Quality,
+                RecordKind::Raw, RecordKind::Sequence, RecordKind::Raw, RecordKind::Quality,
+            ]);
+
+            let output_file_name = format!("test_output_{}.fastq", codec);
+            unpack_fastq_from_file(&compressed_file_name, &output_file_name, None).unwrap();
+            assert_eq!(std::fs::read_to_string(&output_file_name).unwrap(), input);
+
+            let err = unpack_from_file(&compressed_file_name, &output_file_name, None).unwrap_err();
+            assert!(matches!(err.downcast_ref::<ContainerError>(), Some(ContainerError::FormatMismatch { .. })));
+
+            std::fs::remove_file(input_file_name).unwrap();
+            std::fs::remove_file(compressed_file_name).unwrap();
+            std::fs::remove_file(output_file_name).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_fastq_rejects_malformed_records() {
+        let inputs = ["@read1\nACGT\n+\nIIII\n@read2\nACGT\n", "read1\nACGT\n+\nIIII\n", "@read1\nACGT\nIIII\n+\n", "@read1\nACGT\n+\nIIIII\n"];
+        for (i, input) in inputs.iter().enumerate() {
+            let input_file_name = format!("test_input_malformed_{}.fastq", i);
+            std::fs::write(&input_file_name, input).unwrap();
+
+            let compressed_file_name = format!("test_compressed_malformed_{}.fastq", i);
+            let err = compress_fastq_to_file(&input_file_name, &compressed_file_name, &DNA_TEST_MODE, "ascii", None).unwrap_err();
+            assert!(matches!(err.downcast_ref::<CompressionError>(), Some(CompressionError::MalformedRecord(_))));
+
+            std::fs::remove_file(input_file_name).unwrap();
+            let _ = std::fs::remove_file(compressed_file_name);
+        }
+    }
+
+    #[test]
+    fn test_fastq_quality_binning() {
+        let input = "@read1\nACGTACGTAC\n+\nIJIJ#FEFG5\n";
+        let input_file_name = "test_input_binned.fastq";
+        std::fs::write(input_file_name, input).unwrap();
+
+        let compressed_file_name = "test_compressed_binned.fastq";
+        let binning = QualityBinning::illumina();
+        compress_fastq_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii", Some(&binning)).unwrap();
+
+        let mut reader = BufReader::new(File::open(compressed_file_name).unwrap());
+        assert_eq!(Header::read_from(&mut reader).unwrap().quality_binning, Some(binning));
+
+        let output_file_name = "test_output_binned.fastq";
+        unpack_fastq_from_file(compressed_file_name, output_file_name, None).unwrap();
+        assert_eq!(std::fs::read_to_string(output_file_name).unwrap(), "@read1\nACGTACGTAC\n+\nIIII'FFFF7\n");
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_compress_and_unpack_span_multiple_batches() {
+        let line_count = 2 * BATCH_BYTES / 60;
+        let input: String = (0..line_count).map(|i| format!("{}{}\n", ["ACGT", "TTGA", "NNNN"][i % 3].repeat(14), &"GATTACA"[..i % 7])).collect();
+        let input_file_name = "test_input_batches.txt";
+        std::fs::write(input_file_name, &input).unwrap();
+
+        let compressed_file_name = "test_compressed_batches.txt";
+        compress_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "2bit").unwrap();
+
+        let output_file_name = "test_output_batches.txt";
+        unpack_from_file(compressed_file_name, output_file_name, None).unwrap();
+        assert!(std::fs::read_to_string(output_file_name).unwrap() == input);
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_long_lines_match_sequential_encoding() {
+        let codec = AsciiCodec::new(&DNA_TEST_MODE).unwrap();
+        let line = format!("{}acgtRYNN{}", "GATTACA".repeat(CHUNK_BASES / 7), "TTAGGC".repeat(CHUNK_BASES / 5));
+
+        let mut sequential = Vec::new();
+        codec.encode(line.to_uppercase().as_bytes(), &mut sequential).unwrap();
+        let record = sequence_record(&line, &codec).unwrap();
+        assert_eq!(record.kind, RecordKind::MaskedSequence);
+        assert!(record.payload.ends_with(&sequential));
+
+        let record = sequence_record(&line.to_uppercase(), &codec).unwrap();
+        assert_eq!(record.payload, sequential);
+        assert_eq!(unpack_record(&record, &codec, LineEnding::Lf).unwrap(), line.to_uppercase().as_bytes());
+    }
+
+    #[test]
+    fn test_fasta_line_wrapping_round_trip() {
+        let input = ">chr1 wrapped at 8\nACGTACGT\nACGTacgt\nNNNNACGT\nACG\n>empty\n>chr2 irregular\nGATTACA\n\nGA\nTTACAGATTACA\n";
+        let input_file_name = "test_input_wrapping.fasta";
+        std::fs::write(input_file_name, input).unwrap();
+
+        for codec in ["ascii", "2bit"] {
+            let compressed_file_name = format!("test_compressed_wrapping_{}.fasta", codec);
+            compress_fasta_to_file(input_file_name, &compressed_file_name, &DNA_TEST_MODE, codec).unwrap();
+
+            let mut reader = BufReader::new(File::open(&compressed_file_name).unwrap());
+            Header::read_from(&mut reader).unwrap();
+            let kinds: Vec<_> = Record::read_all(&mut reader).unwrap().into_iter()
+                .map(|record| record.kind)
+                .filter(|kind| !kind.is_metadata())
+                .collect();
+            assert_eq!(kinds, vec![
+                RecordKind::Raw, RecordKind::WrappedSequence, RecordKind::Raw, RecordKind::Raw, RecordKind::WrappedSequence,
+            ]);
+
+            let output_file_name = format!("test_output_wrapping_{}.fasta", codec);
+            unpack_fasta_from_file(&compressed_file_name, &output_file_name, None, None).unwrap();
+            assert_eq!(std::fs::read_to_string(&output_file_name).unwrap(), input);
+
+            std::fs::remove_file(compressed_file_name).unwrap();
+            std::fs::remove_file(output_file_name).unwrap();
+        }
+
+        std::fs::remove_file(input_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_line_endings_round_trip() {
+        let inputs = [
+            (SourceFormat::Text, "ACGT\r\nGATTACA\r\n\r\nNNNN\r\n"),
+            (SourceFormat::Text, "ACGT\nGATTACA"),
+            (SourceFormat::Text, ""),
+            (SourceFormat::Fasta, ">seq1 first\r\nACGT\r\nAC\r\n>seq2\r\nGATTACA\r\n"),
+            (SourceFormat::Fasta, ">seq1\nACGT\nAC"),
+            (SourceFormat::Fastq, "@read1\r\nACGT\r\n+\r\nIIII\r\n"),
+            (SourceFormat::Fastq, "@read1\nACGT\n+\nIIII"),
+        ];
+        for (i, (format, input)) in inputs.iter().enumerate() {
+            let input_file_name = format!("test_input_line_endings_{}", i);
+            std::fs::write(&input_file_name, input).unwrap();
+
+            let compressed_file_name = format!("test_compressed_line_endings_{}", i);
+            let output_file_name = format!("test_output_line_endings_{}", i);
+            match format {
+                SourceFormat::Text => {
+                    compress_to_file(&input_file_name, &compressed_file_name, &DNA_TEST_MODE, "ascii").unwrap();
+                    unpack_from_file(&compressed_file_name, &output_file_name, None).unwrap();
+                }
+                SourceFormat::Fasta => {
+                    compress_fasta_to_file(&input_file_name, &compressed_file_name, &DNA_TEST_MODE, "2bit").unwrap();
+                    unpack_fasta_from_file(&compressed_file_name, &output_file_name, None, None).unwrap();
+                }
+                SourceFormat::Fastq => {
+                    compress_fastq_to_file(&input_file_name, &compressed_file_name, &DNA_TEST_MODE, "ascii", None).unwrap();
+                    unpack_fastq_from_file(&compressed_file_name, &output_file_name, None).unwrap();
+                }
+            }
+            assert_eq!(std::fs::read_to_string(&output_file_name).unwrap(), *input);
+
+            std::fs::remove_file(input_file_name).unwrap();
+            std::fs::remove_file(compressed_file_name).unwrap();
+            std::fs::remove_file(output_file_name).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_mixed_line_endings_are_rejected() {
+        let input_file_name = "test_input_mixed_line_endings.txt";
+        std::fs::write(input_file_name, "ACGT\nACGT\r\n").unwrap();
+
+        let compressed_file_name = "test_compressed_mixed_line_endings.txt";
+        let err = compress_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii").unwrap_err();
+        assert_eq!(err.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(input_file_name).unwrap();
+        let _ = std::fs::remove_file(compressed_file_name);
+    }
+
+    #[test]
+    fn test_joined_fasta_is_smaller() {
+        let sequence: Vec<String> = (0..100).map(|_| "ACGTACGTAC".repeat(6) + "A").collect();
+        let joined = wrapped_sequence_record(&sequence, &AsciiCodec::new(&DNA_TEST_MODE).unwrap()).unwrap();
+        let per_line: usize = sequence.iter()
+            .map(|line| sequence_record(line, &AsciiCodec::new(&DNA_TEST_MODE).unwrap()).unwrap().payload.len())
+            .sum();
+        assert!(joined.payload.len() < per_line);
+    }
+
+    #[test]
+    fn test_fasta_reflow() {
+        let input = ">chr1\nACGTACGT\nACGTacgt\nNNNNACGT\nACG\n>empty\n>chr2\nGATTACA\n\nGA\n";
+        let input_file_name = "test_input_reflow.fasta";
+        std::fs::write(input_file_name, input).unwrap();
+
+        let compressed_file_name = "test_compressed_reflow.fasta";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "2bit").unwrap();
+
+        let output_file_name = "test_output_reflow.fasta";
+        let expected = [
+            (5, ">chr1\nACGTA\nCGTAC\nGTacg\ntNNNN\nACGTA\nCG\n>empty\n>chr2\nGATTA\nCAGA\n"),
+            (0, ">chr1\nACGTACGTACGTacgtNNNNACGTACG\n>empty\n>chr2\nGATTACAGA\n"),
+            (9, ">chr1\nACGTACGTA\nCGTacgtNN\nNNACGTACG\n>empty\n>chr2\nGATTACAGA\n"),
+        ];
+        for (width, expected) in expected {
+            unpack_fasta_from_file(compressed_file_name, output_file_name, None, Some(width)).unwrap();
+            assert_eq!(std::fs::read_to_string(output_file_name).unwrap(), expected);
+        }
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_fasta_blocks() {
+        let line = "A".repeat(FASTA_BLOCK_BASES / 2 + 1);
+        let lines: Vec<String> = vec![">chr1".into(), line.clone(), line.clone(), line.clone(), ">chr2".into(), ">chr3".into(), "ACGT".into()];
+        let block_sizes: Vec<usize> = fasta_blocks(&lines).iter().map(|block| block.len()).collect();
+        assert_eq!(block_sizes, vec![1, 1, 1, 1, 1, 1, 1]);
+
+        let lines: Vec<String> = vec![">chr1".into(), "ACGT".into(), "".into(), "AC".into()];
+        let block_sizes: Vec<usize> = fasta_blocks(&lines).iter().map(|block| block.len()).collect();
+        assert_eq!(block_sizes, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_extract_region() {
+        let chr1: String = (0..3 * FASTA_BLOCK_BASES).map(|i| ["A", "C", "G", "T", "n"][i * 7 % 5]).collect();
+        let chr1_lines: Vec<&str> = chr1.as_bytes().chunks(60).map(|line| std::str::from_utf8(line).unwrap()).collect();
+        let input = format!(">chr1 description\n{}\n>chr2\nGATTACA\nGA\n>empty\n", chr1_lines.join("\n"));
+        let input_file_name = "test_input_extract.fasta";
+        std::fs::write(input_file_name, &input).unwrap();
+
+        let compressed_file_name = "test_compressed_extract.fasta";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "2bit").unwrap();
+
+        let output_file_name = "test_output_extract.fasta";
+        unpack_fasta_from_file(compressed_file_name, output_file_name, None, None).unwrap();
+        assert_eq!(std::fs::read_to_string(output_file_name).unwrap(), input);
+
+        let mut reader = BufReader::new(File::open(compressed_file_name).unwrap());
+        let index = SequenceIndex::load(&mut reader).unwrap();
+        assert_eq!(index.entries.iter().map(|entry| entry.len).collect::<Vec<_>>(), vec![chr1.len() as u64, 9, 0]);
+        assert!(index.entries[0].blocks.len() > 3);
+
+        let regions = [
+            ("chr1:1-10", &chr1[..10]),
+            ("chr1:65530-65600", &chr1[65529..65600]),
+            ("chr1:196000", &chr1[195999..]),
+            ("chr2:3-100", "TTACAGA"),
+            ("chr2", "GATTACAGA"),
+            ("empty", ""),
+        ];
+        for (region, expected) in regions {
+            extract_region_from_file(compressed_file_name, region, output_file_name, 0).unwrap();
+            let expected = match expected {
+                "" => format!(">{}\n", region),
+                _ => format!(">{}\n{}\n", region, expected),
+            };
+            assert_eq!(std::fs::read_to_string(output_file_name).unwrap(), expected);
+        }
+
+        extract_region_from_file(compressed_file_name, "chr2", output_file_name, 4).unwrap();
+        assert_eq!(std::fs::read_to_string(output_file_name).unwrap(), ">chr2\nGATT\nACAG\nA\n");
+
+        let err = extract_region_from_file(compressed_file_name, "chr3", output_file_name, 0).unwrap_err();
+        assert!(matches!(err.downcast_ref::<RegionError>(), Some(RegionError::UnknownSequence(_))));
+        let err = extract_region_from_file(compressed_file_name, "chr2:10-12", output_file_name, 0).unwrap_err();
+        assert!(matches!(err.downcast_ref::<RegionError>(), Some(RegionError::OutOfRange { .. })));
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(output_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_fasta_index_files() {
+        let input = ">chr1 first\nACGTACGTAC\nACGTACGTAC\nACG\n>chr2\nGGGGCCCC\nTT\n>empty\n";
+        let input_file_name = "test_input_fai.fasta";
+        std::fs::write(input_file_name, input).unwrap();
+
+        let compressed_file_name = "test_compressed_fai.fasta";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii").unwrap();
+
+        let mut reader = BufReader::new(File::open(compressed_file_name).unwrap());
+        let stored = SequenceIndex::load(&mut reader).unwrap();
+        assert_eq!(scan_sequence_index(compressed_file_name).unwrap(), stored);
+
+        write_fasta_index_files(compressed_file_name, &stored).unwrap();
+        let (fai_path, block_offsets_path) = fasta_index_paths(compressed_file_name);
+        assert_eq!(
+            std::fs::read_to_string(&fai_path).unwrap(),
+            "chr1\t23\t12\t10\t11\nchr2\t10\t44\t8\t9\nempty\t0\t63\t0\t0\n"
+        );
+        for entry in &stored.entries[..2] {
+            let first_line = &input[entry.original_offset as usize..][..entry.line_width as usize];
+            assert!(first_line.ends_with('\n') && !first_line.starts_with('>'));
+        }
+
+        let block_offsets = std::fs::read_to_string(&block_offsets_path).unwrap();
+        let first_block: Vec<&str> = block_offsets.lines().next().unwrap().split('\t').collect();
+        assert_eq!(&first_block[..3], &["chr1", "0", "23"]);
+        let record = read_record_at(&mut reader, first_block[3].parse().unwrap()).unwrap();
+        assert_eq!(record.kind, RecordKind::WrappedSequence);
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(fai_path).unwrap();
+        std::fs::remove_file(block_offsets_path).unwrap();
+    }
+
+    #[test]
+    fn test_fasta_index_files_line_layouts() {
+        let input = ">chr1\r\nACGTACGTAC\r\nACG\r\n>chr2\r\nGGGG\r\n";
+        let input_file_name = "test_input_fai_crlf.fasta";
+        std::fs::write(input_file_name, input).unwrap();
+        let compressed_file_name = "test_compressed_fai_crlf.fasta";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii").unwrap();
+
+        let index = scan_sequence_index(compressed_file_name).unwrap();
+        write_fasta_index_files(compressed_file_name, &index).unwrap();
+        let (fai_path, block_offsets_path) = fasta_index_paths(compressed_file_name);
+        assert_eq!(std::fs::read_to_string(&fai_path).unwrap(), "chr1\t13\t7\t10\t12\nchr2\t4\t31\t4\t6\n");
+        assert!(input[index.entries[0].original_offset as usize..].starts_with("ACGTACGTAC\r\n"));
+        assert!(input[index.entries[1].original_offset as usize..].starts_with("GGGG\r\n"));
+
+        std::fs::write(input_file_name, ">chr1\nACGT\nAC\nACGT\n").unwrap();
+        compress_fasta_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii").unwrap();
+        write_fasta_index_files(compressed_file_name, &scan_sequence_index(compressed_file_name).unwrap()).unwrap();
+        assert!(!Path::new(&fai_path).exists());
+        assert!(Path::new(&block_offsets_path).exists());
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        std::fs::remove_file(block_offsets_path).unwrap();
+    }
+
+    #[test]
+    fn test_unpack_detects_corruption_and_truncation() {
+        let input_file_name = "test_input_corrupt.fasta";
+        std::fs::write(input_file_name, ">chr1\nACGTACGTAC\nACG\n>chr2\nGGGGCCCC\n").unwrap();
+
+        let compressed_file_name = "test_compressed_corrupt.fasta";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii").unwrap();
+        let compressed = std::fs::read(compressed_file_name).unwrap();
+        let index = SequenceIndex::load(&mut io::Cursor::new(&compressed)).unwrap();
+
+        let output_file_name = "test_output_corrupt.fasta";
+        let mut corrupt = compressed.clone();
+        corrupt[index.entries[1].blocks[0].offset as usize + 3] ^= 0x01;
+        std::fs::write(compressed_file_name, &corrupt).unwrap();
+        let err = unpack_fasta_from_file(compressed_file_name, output_file_name, None, None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ContainerError>(),
+            Some(ContainerError::RecordChecksumMismatch(record)) if record == "WrappedSequence record 4 (>chr2)"
+        ));
+
+        let data_len = index.entries[1].blocks[0].offset as usize;
+        std::fs::write(compressed_file_name, &compressed[..data_len]).unwrap();
+        let err = unpack_fasta_from_file(compressed_file_name, output_file_name, None, Some(60)).unwrap_err();
+        assert!(matches!(err.downcast_ref::<ContainerError>(), Some(ContainerError::Truncated)));
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+        let _ = std::fs::remove_file(output_file_name);
+    }
+
+    #[test]
+    fn test_archive_info() {
+        let input_file_name = "test_input_info.fastq";
+        std::fs::write(input_file_name, "@read1\nACGTN\n+\nIIII#\n@read2\nNNGTA\n+\nIIIII\n@read3\nACG\n+\nIII\n").unwrap();
+        let compressed_file_name = "test_compressed_info.fastq";
+        compress_fastq_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii", None).unwrap();
+
+        let info = archive_info(compressed_file_name).unwrap();
+        assert_eq!(info.header.format, SourceFormat::Fastq);
+        assert_eq!(info.summary.original_len, 59);
+        assert_eq!(info.compressed_len, std::fs::metadata(compressed_file_name).unwrap().len());
+        assert_eq!(info.summary.sequence_count(), 3);
+        assert_eq!(info.summary.bases, 13);
+        assert_eq!(info.summary.n_count, 3);
+        assert_eq!(info.summary.length_runs(), &[(5, 2), (3, 1)]);
+        assert!(info.index.is_none());
+        assert!(info.to_string().contains("Record lengths:\n  5 x 2\n  3 x 1\n"));
+
+        let input_file_name = "test_input_info.fasta";
+        std::fs::write(input_file_name, ">chr1\nACGTN\nAC\n>chr2\n>chr3\nnnnA\n").unwrap();
+        let compressed_file_name = "test_compressed_info.fasta";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "2bit").unwrap();
+
+        let info = archive_info(compressed_file_name).unwrap();
+        assert_eq!(info.summary.length_runs(), &[(7, 1), (0, 1), (4, 1)]);
+        assert_eq!((info.summary.bases, info.summary.n_count), (11, 4));
+        assert!(info.to_string().contains("Codec: 2bit\n"));
+        assert!(info.to_string().contains("Record lengths:\n  chr1\t7\n  chr2\t0\n  chr3\t4\n"));
+
+        std::fs::remove_file("test_input_info.fastq").unwrap();
+        std::fs::remove_file("test_compressed_info.fastq").unwrap();
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_unpack_to_writer() {
+        let input_file_name = "test_input_unpack_to_writer.fasta";
+        std::fs::write(input_file_name, ">seq1\nACGTAC\nGT\n>seq2\nNNNN\n").unwrap();
+        let compressed_file_name = "test_compressed_unpack_to_writer.fasta";
+        compress_fasta_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii").unwrap();
+
+        let unpacked = unpack_to_writer(compressed_file_name, SourceFormat::Fasta, None, None, Vec::new()).unwrap();
+        assert_eq!(unpacked, b">seq1\nACGTAC\nGT\n>seq2\nNNNN\n");
+        let reflowed = unpack_to_writer(compressed_file_name, SourceFormat::Fasta, None, Some(0), Vec::new()).unwrap();
+        assert_eq!(reflowed, b">seq1\nACGTACGT\n>seq2\nNNNN\n");
+        assert!(unpack_to_writer(compressed_file_name, SourceFormat::Text, None, None, Vec::new()).is_err());
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_unpack_piped_archive() {
+        let input_file_name = "test_input_piped.fastq";
+        let original = "@read1\nACGT\n+\nIIII\n";
+        std::fs::write(input_file_name, original).unwrap();
+        let compressed_file_name = "test_compressed_piped.fastq";
+        compress_fastq_to_file(input_file_name, compressed_file_name, &DNA_TEST_MODE, "ascii", None).unwrap();
+
+        // A plain `Read` over the bytes stands in for stdin, which cannot seek.
+        let piped = || -> Box<dyn Read> { Box::new(io::Cursor::new(std::fs::read(compressed_file_name).unwrap())) };
+        let (header, reader) = read_archive_header(piped()).unwrap();
+        assert_eq!(header.format, SourceFormat::Fastq);
+        let unpacked = unpack_reader_to_writer("-", reader, header.format, None, None, Vec::new()).unwrap();
+        assert_eq!(unpacked, original.as_bytes());
+
+        let (header, reader) = read_archive_header(piped()).unwrap();
+        verify_reader("-", reader, Some(input_file_name), header.format, None).unwrap();
+        assert!(read_archive_header(Box::new(original.as_bytes())).is_err());
+
+        std::fs::remove_file(input_file_name).unwrap();
+        std::fs::remove_file(compressed_file_name).unwrap();
+    }
+}
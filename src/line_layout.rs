@@ -0,0 +1,204 @@
+use std::io::{self, Write};
+
+use crate::container::LineEnding;
+use crate::errors::CompressionError;
+use crate::varint::{read_varint, write_varint};
+
+// Line lengths of a wrapped block, run-length encoded as (length, count) so a
+// regular wrap width costs a single run plus one for the final short line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineLayout {
+    runs: Vec<(usize, usize)>,
+}
+
+impl LineLayout {
+    pub fn from_lines<S: AsRef<str>>(lines: &[S]) -> Self {
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for line in lines {
+            let len = line.as_ref().len();
+            match runs.last_mut() {
+                Some((run_len, count)) if *run_len == len => *count += 1,
+                _ => runs.push((len, 1)),
+            }
+        }
+        Self { runs }
+    }
+
+    pub fn runs(&self) -> &[(usize, usize)] {
+        &self.runs
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.runs.iter().map(|&(_, count)| count).sum()
+    }
+
+    pub fn first_line_len(&self) -> usize {
+        self.runs.first().map_or(0, |&(len, _)| len)
+    }
+
+    pub fn total_len(&self) -> usize {
+        self.runs.iter().map(|&(len, count)| len.saturating_mul(count)).fold(0, usize::saturating_add)
+    }
+
+    pub fn wrap(&self, sequence: &[u8], line_ending: LineEnding) -> Result<Vec<u8>, CompressionError> {
+        if self.total_len() != sequence.len() {
+            return Err(CompressionError::InvalidEncoding("line layout does not match the sequence length".to_string()));
+        }
+
+        let mut wrapped = Vec::with_capacity(sequence.len() + self.line_count() * line_ending.as_bytes().len());
+        let mut rest = sequence;
+        for (i, len) in self.runs.iter().flat_map(|&(len, count)| std::iter::repeat_n(len, count)).enumerate() {
+            let (line, remaining) = rest.split_at(len);
+            if i > 0 {
+                wrapped.extend_from_slice(line_ending.as_bytes());
+            }
+            wrapped.extend_from_slice(line);
+            rest = remaining;
+        }
+        Ok(wrapped)
+    }
+
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.runs.len() as u64);
+        for &(len, count) in &self.runs {
+            write_varint(out, len as u64);
+            write_varint(out, count as u64);
+        }
+    }
+
+    pub fn read_from(input: &mut &[u8]) -> Result<Self, CompressionError> {
+        let run_count = read_length(input)?;
+        let mut runs = Vec::with_capacity(run_count.min(input.len()));
+        for _ in 0..run_count {
+            runs.push((read_length(input)?, read_length(input)?));
+        }
+        Ok(Self { runs })
+    }
+}
+
+// Rewraps sequences at a fixed width as they stream through; a width of zero
+// writes each sequence on a single line.
+pub struct ReflowWriter<W: Write> {
+    inner: W,
+    width: usize,
+    column: usize,
+    line_ending: LineEnding,
+}
+
+impl<W: Write> ReflowWriter<W> {
+    pub fn new(inner: W, width: usize) -> Self {
+        Self { inner, width, column: 0, line_ending: LineEnding::Lf }
+    }
+
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    pub fn write_sequence(&mut self, mut sequence: &[u8]) -> io::Result<()> {
+        while !sequence.is_empty() {
+            if self.width == 0 {
+                self.column += sequence.len();
+                return self.inner.write_all(sequence);
+            }
+            if self.column == self.width {
+                self.inner.write_all(self.line_ending.as_bytes())?;
+                self.column = 0;
+            }
+            let (line, rest) = sequence.split_at(sequence.len().min(self.width - self.column));
+            self.inner.write_all(line)?;
+            self.column += line.len();
+            sequence = rest;
+        }
+        Ok(())
+    }
+
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        self.end_sequence()?;
+        self.inner.write_all(line)?;
+        self.inner.write_all(self.line_ending.as_bytes())
+    }
+
+    pub fn end_sequence(&mut self) -> io::Result<()> {
+        if self.column > 0 {
+            self.column = 0;
+            self.inner.write_all(self.line_ending.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.end_sequence()?;
+        Ok(self.inner)
+    }
+}
+
+fn read_length(input: &mut &[u8]) -> Result<usize, CompressionError> {
+    read_varint(input)
+        .map(|value| value as usize)
+        .ok_or_else(|| CompressionError::InvalidEncoding("truncated line layout".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_lines() {
+        let layout = LineLayout::from_lines(&["ACGT", "ACGT", "ACGT", "AC"]);
+        assert_eq!(layout.runs, vec![(4, 3), (2, 1)]);
+        assert_eq!(layout.total_len(), 14);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let lines = ["ACGTAC", "GTACGT", "", "ACG", "TTTTTT", "A"];
+        let layout = LineLayout::from_lines(&lines);
+
+        let mut bytes = Vec::new();
+        layout.write_to(&mut bytes);
+        let mut input = bytes.as_slice();
+        let decoded_layout = LineLayout::read_from(&mut input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(decoded_layout, layout);
+
+        let wrapped = decoded_layout.wrap(lines.concat().as_bytes(), LineEnding::Lf).unwrap();
+        assert_eq!(wrapped, lines.join("\n").as_bytes());
+        let wrapped = decoded_layout.wrap(lines.concat().as_bytes(), LineEnding::CrLf).unwrap();
+        assert_eq!(wrapped, lines.join("\r\n").as_bytes());
+    }
+
+    #[test]
+    fn test_invalid_layout() {
+        let layout = LineLayout::from_lines(&["ACGT", "AC"]);
+        assert!(matches!(layout.wrap(b"ACGTACG", LineEnding::Lf), Err(CompressionError::InvalidEncoding(_))));
+
+        let mut input: &[u8] = &[1, 60];
+        assert!(matches!(LineLayout::read_from(&mut input), Err(CompressionError::InvalidEncoding(_))));
+    }
+
+    #[test]
+    fn test_reflow_writer() {
+        let mut writer = ReflowWriter::new(Vec::new(), 4);
+        writer.write_line(b">seq1").unwrap();
+        writer.write_sequence(b"ACGTAC").unwrap();
+        writer.write_sequence(b"GT").unwrap();
+        writer.write_line(b">seq2").unwrap();
+        writer.write_sequence(b"ACG").unwrap();
+        assert_eq!(writer.into_inner().unwrap(), b">seq1\nACGT\nACGT\n>seq2\nACG\n");
+
+        let mut writer = ReflowWriter::new(Vec::new(), 0);
+        writer.write_line(b">seq1").unwrap();
+        writer.write_sequence(b"ACGTAC").unwrap();
+        writer.write_sequence(b"GT").unwrap();
+        writer.write_line(b">empty").unwrap();
+        writer.write_line(b">seq2").unwrap();
+        writer.write_sequence(b"A").unwrap();
+        assert_eq!(writer.into_inner().unwrap(), b">seq1\nACGTACGT\n>empty\n>seq2\nA\n");
+
+        let mut writer = ReflowWriter::new(Vec::new(), 4).with_line_ending(LineEnding::CrLf);
+        writer.write_line(b">seq1").unwrap();
+        writer.write_sequence(b"ACGTAC").unwrap();
+        assert_eq!(writer.into_inner().unwrap(), b">seq1\r\nACGT\r\nAC\r\n");
+    }
+}
@@ -1,48 +1,617 @@
+use glob::Pattern;
 use structopt::StructOpt;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
 use std::path::Path;
+use std::process;
 
 use compact_sequence::file_extensions::*;
+use compact_sequence::codec::{available_codecs, default_codec};
+use compact_sequence::container::{Header, SourceFormat};
 use compact_sequence::mode::Mode;
+use compact_sequence::{archive_info, extract_region_from_file, fasta_index_paths, read_archive_header, scan_sequence_index, unpack_reader_to_writer, verify_reader, write_fasta_index_files};
+use compact_sequence::quality::QualityBinning;
+use compact_sequence::bundle::{is_bundle, Bundle};
+use compact_sequence::stdio::{create_output, is_stdio, open_input, STDIO_PATH};
+use compact_sequence::processors::directory_processing::{compress_directory, unpack_directory, verify_directory, DirectoryReport, NamingScheme, UnsupportedFiles};
 use compact_sequence::processors::processor::{
     DirectoryProcessor,
     FastaProcessor,
+    FastqProcessor,
     Processor,
-    TextProcessor, 
+    TextProcessor,
 };
 
+const DEFAULT_EXTRACT_LINE_WIDTH: usize = 60;
+
+// Options that only apply when the input is a directory.
+#[derive(Debug, Default, PartialEq, StructOpt)]
+struct DirectoryArgs {
+    /// Output file name template for directories, using {stem}, {ext} and {name} of each input file
+    #[structopt(long)]
+    naming: Option<NamingScheme>,
+    /// Stop at the first file that fails in a directory
+    #[structopt(long, overrides_with = "keep-going")]
+    fail_fast: bool,
+    /// Process every file of a directory even when some fail (the default), overrides an earlier --fail-fast
+    #[structopt(long, overrides_with = "fail-fast")]
+    keep_going: bool,
+    /// Only process directory files matching this glob, a pattern without `/` matches the file name
+    #[structopt(long, number_of_values = 1)]
+    include: Vec<Pattern>,
+    /// Skip directory files and subdirectories matching this glob
+    #[structopt(long, number_of_values = 1)]
+    exclude: Vec<Pattern>,
+    /// Descend at most this many levels into a directory, 1 processes only its top level files
+    #[structopt(long)]
+    max_depth: Option<usize>,
+    /// Follow symbolic links inside a directory
+    #[structopt(long)]
+    follow_symlinks: bool,
+    /// What to do with directory files no processor handles: skip, warn or error
+    #[structopt(long, possible_values = &["skip", "warn", "error"])]
+    unsupported: Option<UnsupportedFiles>,
+    /// Pack a compressed directory into a single bundle file at the output path
+    #[structopt(long)]
+    bundle: bool,
+}
+
+impl DirectoryArgs {
+    fn apply(self, processor: DirectoryProcessor) -> DirectoryProcessor {
+        processor.with_naming(self.naming)
+            .with_fail_fast(self.fail_fast && !self.keep_going)
+            .with_include(self.include)
+            .with_exclude(self.exclude)
+            .with_max_depth(self.max_depth)
+            .with_follow_symlinks(self.follow_symlinks)
+            .with_unsupported_files(self.unsupported.unwrap_or_default())
+            .with_bundle(self.bundle)
+    }
+}
+
+// Directories are run directly rather than through `Processor` so their
+// report can be printed.
+enum Target {
+    File(Box<dyn Processor>),
+    Directory(DirectoryProcessor),
+}
+
+// The flags on `Opt` are the original single command interface and are kept
+// as an alias: without a subcommand they are translated into one.
 #[derive(Debug, StructOpt)]
 struct Opt {
+    #[structopt(subcommand)]
+    command: Option<Command>,
     #[structopt(short, long)]
-    input: String,
+    input: Option<String>,
     #[structopt(short, long)]
-    output: String,
+    output: Option<String>,
     #[structopt(short, long)]
     unpack: bool,
-    #[structopt(short, long, default_value = "dna", possible_values = &["rna", "dna"])]
-    mode: Mode,
-}
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let opt = Opt::from_args();
-    let input_path = &opt.input;
-    let output_path = &opt.output;
-    let mode = &opt.mode;
-    println!("Running in {:?} mode", mode);
-
-    let path = Path::new(input_path);
-    let processor: Box<dyn Processor> = match path.extension().and_then(|s| s.to_str()) {
-        Some(ext) if is_fasta_extension(ext) => Box::new(FastaProcessor),
-        Some(ext) if is_text_extension(ext) => Box::new(TextProcessor),
-        _ if path.is_dir() => Box::new(DirectoryProcessor::new(vec!["txt".to_string()])),
+    #[structopt(short, long, possible_values = &["rna", "dna", "protein"])]
+    mode: Option<Mode>,
+    #[structopt(short, long)]
+    codec: Option<String>,
+    #[structopt(long)]
+    quality_binning: Option<QualityBinning>,
+    /// Input format, required when compressing from stdin with `-i -`
+    #[structopt(long, possible_values = &["text", "fasta", "fastq"])]
+    format: Option<SourceFormat>,
+    /// Rewrap FASTA sequences to this width when unpacking, 0 writes each sequence on one line
+    #[structopt(long)]
+    line_width: Option<usize>,
+    /// Extract a region (name, name:start or name:start-end, 1-based) from a compressed FASTA file
+    #[structopt(long)]
+    extract: Option<String>,
+    /// Rebuild the .fai and .cfi companion index files of a compressed FASTA file
+    #[structopt(long)]
+    rebuild_index: bool,
+    /// Decode a compressed file in memory and check it against its stored checksums
+    #[structopt(long, conflicts_with = "unpack")]
+    verify: bool,
+    /// Original file to compare against byte for byte when verifying
+    #[structopt(long, requires = "verify")]
+    original: Option<String>,
+    /// Print the format, sizes and sequence statistics stored in a compressed file
+    #[structopt(long)]
+    info: bool,
+    #[structopt(flatten)]
+    directory: DirectoryArgs,
+}
+
+#[derive(Debug, PartialEq, StructOpt)]
+enum Command {
+    /// Compress a text, FASTA or FASTQ file, or a directory mixing them, optionally packed into one --bundle file
+    Compress {
+        #[structopt(short, long)]
+        input: String,
+        #[structopt(short, long)]
+        output: String,
+        #[structopt(short, long, possible_values = &["rna", "dna", "protein"])]
+        mode: Option<Mode>,
+        #[structopt(short, long)]
+        codec: Option<String>,
+        /// Input format, required when compressing from stdin with `-i -`
+        #[structopt(long, possible_values = &["text", "fasta", "fastq"])]
+        format: Option<SourceFormat>,
+        /// Bin FASTQ quality scores: "illumina" or a table such as 0-19:10,20-93:30
+        #[structopt(long)]
+        quality_binning: Option<QualityBinning>,
+        #[structopt(flatten)]
+        directory: DirectoryArgs,
+    },
+    /// Unpack a compressed file, directory or directory bundle
+    Unpack {
+        #[structopt(short, long)]
+        input: String,
+        #[structopt(short, long)]
+        output: String,
+        /// Expected mode, detected from the file header when omitted
+        #[structopt(short, long, possible_values = &["rna", "dna", "protein"])]
+        mode: Option<Mode>,
+        /// Expected input format, read from the archive header when omitted
+        #[structopt(long, possible_values = &["text", "fasta", "fastq"])]
+        format: Option<SourceFormat>,
+        /// Rewrap FASTA sequences to this width, 0 writes each sequence on one line
+        #[structopt(long)]
+        line_width: Option<usize>,
+        #[structopt(flatten)]
+        directory: DirectoryArgs,
+    },
+    /// Decode a compressed file in memory and check it against its stored checksums
+    Verify {
+        #[structopt(short, long)]
+        input: String,
+        /// Original file to compare against byte for byte
+        #[structopt(long)]
+        original: Option<String>,
+        #[structopt(short, long, possible_values = &["rna", "dna", "protein"])]
+        mode: Option<Mode>,
+        /// Expected input format, read from the archive header when omitted
+        #[structopt(long, possible_values = &["text", "fasta", "fastq"])]
+        format: Option<SourceFormat>,
+        #[structopt(flatten)]
+        directory: DirectoryArgs,
+    },
+    /// Print the format, sizes and sequence statistics stored in a compressed file
+    Info {
+        #[structopt(short, long)]
+        input: String,
+    },
+    /// Extract a region (name, name:start or name:start-end, 1-based) from a compressed FASTA file
+    Extract {
+        #[structopt(short, long)]
+        input: String,
+        region: String,
+        #[structopt(short, long)]
+        output: String,
+        #[structopt(long)]
+        line_width: Option<usize>,
+    },
+    /// Unpack a compressed file to standard output
+    Cat {
+        #[structopt(short, long)]
+        input: String,
+        #[structopt(short, long, possible_values = &["rna", "dna", "protein"])]
+        mode: Option<Mode>,
+        /// Expected input format, read from the archive header when omitted
+        #[structopt(long, possible_values = &["text", "fasta", "fastq"])]
+        format: Option<SourceFormat>,
+        #[structopt(long)]
+        line_width: Option<usize>,
+    },
+    /// Rebuild the .fai and .cfi companion index files of a compressed FASTA file
+    Index {
+        #[structopt(short, long)]
+        input: String,
+    },
+    /// List the members of a directory bundle with their modes and sizes
+    List {
+        #[structopt(short, long)]
+        input: String,
+    },
+    /// Unpack one member of a directory bundle, given by its path inside the bundle
+    ExtractMember {
+        /// Directory bundle to read the member from
+        #[structopt(short, long)]
+        input: String,
+        /// Path of the member inside the bundle, as printed by `list`
+        member: String,
+        /// File to write the member to, `-` for stdout
+        #[structopt(short, long)]
+        output: String,
+        /// Write the member's compressed file as stored instead of unpacking it
+        #[structopt(long)]
+        compressed: bool,
+        /// Expected mode, detected from the member's header when omitted
+        #[structopt(short, long, possible_values = &["rna", "dna", "protein"])]
+        mode: Option<Mode>,
+        /// Rewrap a FASTA member's sequences to this width, 0 writes each sequence on one line
+        #[structopt(long, conflicts_with = "compressed")]
+        line_width: Option<usize>,
+    },
+}
+
+fn legacy_command(opt: Opt) -> Result<Command, Box<dyn Error>> {
+    let input = opt.input.ok_or("either a subcommand or --input is required")?;
+    if opt.rebuild_index {
+        return Ok(Command::Index { input });
+    }
+    if opt.info {
+        return Ok(Command::Info { input });
+    }
+    if opt.verify {
+        return Ok(Command::Verify { input, original: opt.original, mode: opt.mode, format: opt.format, directory: opt.directory });
+    }
+
+    let output = opt.output.ok_or("--output is required")?;
+    if let Some(region) = opt.extract {
+        return Ok(Command::Extract { input, region, output, line_width: opt.line_width });
+    }
+    if opt.unpack {
+        if opt.quality_binning.is_some() {
+            return Err("--quality-binning only applies when compressing".into());
+        }
+        return Ok(Command::Unpack { input, output, mode: opt.mode, format: opt.format, line_width: opt.line_width, directory: opt.directory });
+    }
+    if opt.line_width.is_some() {
+        return Err("--line-width only applies when unpacking".into());
+    }
+    Ok(Command::Compress {
+        input,
+        output,
+        mode: opt.mode,
+        codec: opt.codec,
+        format: opt.format,
+        quality_binning: opt.quality_binning,
+        directory: opt.directory,
+    })
+}
+
+// An explicit format wins over the extension, which stdin does not have.
+fn format_for(input_path: &str, format: Option<SourceFormat>) -> Result<Option<SourceFormat>, Box<dyn Error>> {
+    if format.is_some() {
+        return Ok(format);
+    }
+    if is_stdio(input_path) {
+        return Err("--format is required when reading from stdin".into());
+    }
+    Ok(Path::new(input_path).extension().and_then(|s| s.to_str()).and_then(format_for_extension))
+}
+
+// Archives record their format in the header, so unpacking does not depend on
+// the file name.
+fn archive_format(input_path: &str, format: Option<SourceFormat>) -> Result<Option<SourceFormat>, Box<dyn Error>> {
+    match format {
+        Some(format) => Ok(Some(format)),
+        None if Path::new(input_path).is_dir() || is_bundle(input_path) => Ok(None),
+        None => Ok(Some(Header::read_from(&mut File::open(input_path)?)?.format)),
+    }
+}
+
+// Stdin cannot be opened a second time, so its header is read off the stream
+// for the format and replayed in front of the rest of the archive.
+fn stdin_archive(format: Option<SourceFormat>) -> Result<(SourceFormat, Box<dyn Read>), Box<dyn Error>> {
+    let (header, reader) = read_archive_header(open_input(STDIO_PATH)?)?;
+    Ok((format.unwrap_or(header.format), reader))
+}
+
+fn file_options_only(directory: &DirectoryArgs) -> Result<(), Box<dyn Error>> {
+    match directory == &DirectoryArgs::default() {
+        true => Ok(()),
+        false => Err("--naming, --include and the other directory options only apply to directory input".into()),
+    }
+}
+
+fn processor_for(input_path: &str, format: Option<SourceFormat>, codec: &str, quality_binning: Option<QualityBinning>, line_width: Option<usize>, directory: DirectoryArgs) -> Result<Target, Box<dyn Error>> {
+    let format = format_for(input_path, format)?;
+    let is_dir = format.is_none() && (Path::new(input_path).is_dir() || is_bundle(input_path));
+    if !is_dir {
+        file_options_only(&directory)?;
+    }
+    let processor: Box<dyn Processor> = match format {
+        Some(SourceFormat::Fasta) => Box::new(FastaProcessor::new(codec).with_line_width(line_width)),
+        Some(SourceFormat::Fastq) => {
+            Box::new(FastqProcessor::new(codec).with_quality_binning(quality_binning))
+        }
+        None if is_dir => {
+            return Ok(Target::Directory(directory.apply(DirectoryProcessor::new(supported_extensions(), codec)
+                .with_quality_binning(quality_binning)
+                .with_line_width(line_width))));
+        }
+        _ if quality_binning.is_some() => return Err("--quality-binning only applies to FASTQ input".into()),
+        _ if line_width.is_some() => return Err("--line-width only applies to FASTA input".into()),
+        Some(SourceFormat::Text) => Box::new(TextProcessor::new(codec)),
         _ => return Err("Unsupported file format or invalid path".into()),
     };
+    Ok(Target::File(processor))
+}
 
-    if opt.unpack {
-        processor.unpack(input_path, &output_path, mode)?;
-    } else {
-        processor.compress(input_path, &output_path, mode)?;
+// Any failed file makes the whole run fail, after the report is printed.
+fn print_report(report: DirectoryReport) -> Result<(), Box<dyn Error>> {
+    print!("{}", report);
+    report.into_result()?;
+    Ok(())
+}
+
+// Status messages go to stderr when the output itself is written to stdout.
+fn status(output: &str, message: &str) {
+    match is_stdio(output) {
+        true => eprintln!("{}", message),
+        false => println!("{}", message),
+    }
+}
+
+fn mode_message(mode: Option<&Mode>) -> String {
+    match mode {
+        Some(mode) => format!("Running in {:?} mode", mode),
+        None => "Detecting mode from the compressed file header".to_string(),
     }
+}
 
-    println!("File processing completed!");
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Compress { input, output, mode, codec, format, quality_binning, directory } => {
+            let mode = mode.unwrap_or(Mode::DNA);
+            status(&output, &mode_message(Some(&mode)));
+            let codec = codec.unwrap_or_else(|| default_codec(&mode).to_string());
+            let codecs = available_codecs();
+            if !codecs.contains(&codec) {
+                return Err(format!("Unknown codec '{}', available codecs: {}", codec, codecs.join(", ")).into());
+            }
+            match processor_for(&input, format, &codec, quality_binning, None, directory)? {
+                Target::File(processor) => processor.compress(&input, &output, &mode)?,
+                Target::Directory(directory) => print_report(compress_directory(&directory, &input, &output, &mode)?)?,
+            }
+            status(&output, "File processing completed!");
+        }
+        Command::Unpack { input, output, mode, format, line_width, directory } => {
+            status(&output, &mode_message(mode.as_ref()));
+            let codec = default_codec(mode.as_ref().unwrap_or(&Mode::DNA));
+            if is_stdio(&input) {
+                file_options_only(&directory)?;
+                let (format, reader) = stdin_archive(format)?;
+                unpack_reader_to_writer(&input, reader, format, mode.as_ref(), line_width, create_output(&output)?)?.flush()?;
+            } else {
+                match processor_for(&input, archive_format(&input, format)?, codec, None, line_width, directory)? {
+                    Target::File(processor) => processor.unpack(&input, &output, mode.as_ref())?,
+                    Target::Directory(directory) => print_report(unpack_directory(&directory, &input, &output, mode.as_ref())?)?,
+                }
+            }
+            status(&output, "File processing completed!");
+        }
+        Command::Verify { input, original, mode, format, directory } => {
+            println!("{}", mode_message(mode.as_ref()));
+            let codec = default_codec(mode.as_ref().unwrap_or(&Mode::DNA));
+            if is_stdio(&input) {
+                file_options_only(&directory)?;
+                let (format, reader) = stdin_archive(format)?;
+                verify_reader(&input, reader, original.as_deref(), format, mode.as_ref())?;
+            } else {
+                match processor_for(&input, archive_format(&input, format)?, codec, None, None, directory)? {
+                    Target::File(processor) => processor.verify(&input, original.as_deref(), mode.as_ref())?,
+                    Target::Directory(directory) => {
+                        print_report(verify_directory(&directory, &input, original.as_deref(), mode.as_ref())?)?
+                    }
+                }
+            }
+            match &original {
+                Some(original) => println!("{} unpacks to the same bytes as {}", input, original),
+                None => println!("{} matches its stored checksums", input),
+            }
+        }
+        Command::Info { input } => print!("{}", archive_info(&input)?),
+        Command::Extract { input, region, output, line_width } => {
+            extract_region_from_file(&input, &region, &output, line_width.unwrap_or(DEFAULT_EXTRACT_LINE_WIDTH))?;
+            status(&output, &format!("Extracted {} to {}", region, output));
+        }
+        Command::Cat { input, mode, format, line_width } => {
+            let (format, reader) = match is_stdio(&input) {
+                true => stdin_archive(format)?,
+                false => {
+                    let format = archive_format(&input, format)?.ok_or("cat needs a compressed file, not a directory or bundle")?;
+                    (format, open_input(&input)?)
+                }
+            };
+            let stdout = io::stdout();
+            unpack_reader_to_writer(&input, reader, format, mode.as_ref(), line_width, BufWriter::new(stdout.lock()))?.flush()?;
+        }
+        Command::Index { input } => {
+            write_fasta_index_files(&input, &scan_sequence_index(&input)?)?;
+            let (fai_path, block_offsets_path) = fasta_index_paths(&input);
+            println!("Wrote {} and {}", fai_path, block_offsets_path);
+        }
+        Command::List { input } => {
+            println!("Mode\tPermissions\tOriginal\tCompressed\tPath");
+            for entry in Bundle::open(&input)?.entries() {
+                println!("{:?}\t{:04o}\t{}\t{}\t{}", entry.mode, entry.permissions, entry.original_len, entry.compressed_len, entry.path);
+            }
+        }
+        Command::ExtractMember { input, member, output, compressed, mode, line_width } => {
+            let bundle = Bundle::open(&input)?;
+            let entry = bundle.find(&member)?;
+            match compressed {
+                true => bundle.copy_member(entry, create_output(&output)?)?.flush()?,
+                false => bundle.unpack_member(entry, &output, mode.as_ref(), line_width)?,
+            }
+            status(&output, &format!("Extracted {} to {}", member, output));
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+// Errors are printed with their Display message rather than the Debug output
+// `main` returning a `Result` would give.
+fn main() {
+    let mut opt = Opt::from_args();
+    let result = match opt.command.take() {
+        Some(command) => run(command),
+        None => legacy_command(opt).and_then(run),
+    };
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(args: &[&str]) -> Command {
+        let mut opt = Opt::from_iter_safe(std::iter::once("compact_sequence").chain(args.iter().copied())).unwrap();
+        match opt.command.take() {
+            Some(command) => command,
+            None => legacy_command(opt).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_subcommands() {
+        assert_eq!(
+            command(&["compress", "-i", "in.fa", "-o", "out.fa", "--codec", "2bit"]),
+            Command::Compress { input: "in.fa".into(), output: "out.fa".into(), mode: None, codec: Some("2bit".into()), format: None, quality_binning: None, directory: DirectoryArgs::default() }
+        );
+        assert_eq!(
+            command(&["extract", "-i", "in.fa", "chr1:10-20", "-o", "out.fa"]),
+            Command::Extract { input: "in.fa".into(), region: "chr1:10-20".into(), output: "out.fa".into(), line_width: None }
+        );
+        assert_eq!(command(&["cat", "-i", "in.fq"]), Command::Cat { input: "in.fq".into(), mode: None, format: None, line_width: None });
+        assert!(Opt::from_iter_safe(["compact_sequence", "unpack", "-i", "in.fa"]).is_err());
+    }
+
+    #[test]
+    fn test_legacy_flags() {
+        assert_eq!(
+            command(&["-i", "in.txt", "-o", "out.txt", "-m", "rna"]),
+            Command::Compress { input: "in.txt".into(), output: "out.txt".into(), mode: Some(Mode::RNA), codec: None, format: None, quality_binning: None, directory: DirectoryArgs::default() }
+        );
+        assert_eq!(
+            command(&["-i", "in.fa", "-o", "out.fa", "-u", "--line-width", "60"]),
+            Command::Unpack { input: "in.fa".into(), output: "out.fa".into(), mode: None, format: None, line_width: Some(60), directory: DirectoryArgs::default() }
+        );
+        assert_eq!(
+            command(&["-i", "in.fa", "--verify", "--original", "orig.fa"]),
+            Command::Verify { input: "in.fa".into(), original: Some("orig.fa".into()), mode: None, format: None, directory: DirectoryArgs::default() }
+        );
+        assert_eq!(command(&["-i", "in.fa", "--rebuild-index"]), Command::Index { input: "in.fa".into() });
+        assert_eq!(command(&["-i", "in.fa", "--info"]), Command::Info { input: "in.fa".into() });
+
+        let opt = Opt::from_iter_safe(["compact_sequence", "-i", "in.txt", "-u"]).unwrap();
+        assert!(legacy_command(opt).is_err());
+    }
+
+    #[test]
+    fn test_stdio_format() {
+        assert_eq!(
+            command(&["compress", "-i", "-", "-o", "-", "--format", "fastq"]),
+            Command::Compress { input: "-".into(), output: "-".into(), mode: None, codec: None, format: Some(SourceFormat::Fastq), quality_binning: None, directory: DirectoryArgs::default() }
+        );
+        assert_eq!(
+            command(&["-i", "-", "-o", "out.fa", "-u", "--format", "fasta"]),
+            Command::Unpack { input: "-".into(), output: "out.fa".into(), mode: None, format: Some(SourceFormat::Fasta), line_width: None, directory: DirectoryArgs::default() }
+        );
+        assert!(Opt::from_iter_safe(["compact_sequence", "cat", "-i", "-", "--format", "bam"]).is_err());
+
+        assert_eq!(format_for("-", Some(SourceFormat::Text)).unwrap(), Some(SourceFormat::Text));
+        assert_eq!(format_for("reads.fq", None).unwrap(), Some(SourceFormat::Fastq));
+        assert_eq!(format_for("reads.fq", Some(SourceFormat::Fasta)).unwrap(), Some(SourceFormat::Fasta));
+        assert!(format_for("-", None).is_err());
+        assert!(processor_for("-", None, "ascii", None, None, DirectoryArgs::default()).is_err());
+    }
+
+    #[test]
+    fn test_directory_args() {
+        let naming: NamingScheme = "{name}.cseq".parse().unwrap();
+        assert_eq!(
+            command(&["compress", "-i", "project", "-o", "out", "--naming", "{name}.cseq"]),
+            Command::Compress {
+                input: "project".into(),
+                output: "out".into(),
+                mode: None,
+                codec: None,
+                format: None,
+                quality_binning: None,
+                directory: DirectoryArgs { naming: Some(naming.clone()), ..DirectoryArgs::default() },
+            }
+        );
+        assert!(Opt::from_iter_safe(["compact_sequence", "compress", "-i", "project", "-o", "out", "--naming", "out"]).is_err());
+
+        let directory = DirectoryArgs { naming: Some(naming), ..DirectoryArgs::default() };
+        assert!(processor_for("in.fa", None, "ascii", None, None, directory).is_err());
+
+        assert_eq!(
+            command(&["-i", "project", "--verify", "--fail-fast"]),
+            Command::Verify {
+                input: "project".into(),
+                original: None,
+                mode: None,
+                format: None,
+                directory: DirectoryArgs { fail_fast: true, ..DirectoryArgs::default() },
+            }
+        );
+        // The last of --fail-fast and --keep-going wins.
+        for (args, fail_fast) in [(["--fail-fast", "--keep-going"], false), (["--keep-going", "--fail-fast"], true)] {
+            match command(&[&["verify", "-i", "project"][..], &args[..]].concat()) {
+                Command::Verify { directory, .. } => {
+                    assert_eq!(directory.apply(DirectoryProcessor::new(supported_extensions(), "ascii")).fail_fast(), fail_fast)
+                }
+                other => panic!("expected verify, got {:?}", other),
+            }
+        }
+
+        let args = ["compress", "-i", "project", "-o", "out", "--include", "*.fa", "--include", "reads/*", "--exclude", "tmp", "--max-depth", "2", "--unsupported", "warn"];
+        match command(&args) {
+            Command::Compress { directory, .. } => assert_eq!(
+                directory,
+                DirectoryArgs {
+                    include: vec![Pattern::new("*.fa").unwrap(), Pattern::new("reads/*").unwrap()],
+                    exclude: vec![Pattern::new("tmp").unwrap()],
+                    max_depth: Some(2),
+                    unsupported: Some(UnsupportedFiles::Warn),
+                    ..DirectoryArgs::default()
+                }
+            ),
+            command => panic!("unexpected command {:?}", command),
+        }
+        assert!(Opt::from_iter_safe(["compact_sequence", "compress", "-i", "project", "-o", "out", "--include", "[a"]).is_err());
+    }
+
+    #[test]
+    fn test_archive_format_from_header() {
+        let archive = "test_archive_format.cs";
+        let mut bytes = Vec::new();
+        Header::new(&Mode::DNA, "ascii", SourceFormat::Fastq).write_to(&mut bytes).unwrap();
+        std::fs::write(archive, bytes).unwrap();
+
+        assert_eq!(archive_format(archive, None).unwrap(), Some(SourceFormat::Fastq));
+        assert_eq!(archive_format(archive, Some(SourceFormat::Text)).unwrap(), Some(SourceFormat::Text));
+        assert_eq!(archive_format(".", None).unwrap(), None);
+
+        std::fs::write(archive, "ACGT\n").unwrap();
+        assert!(archive_format(archive, None).is_err());
+        std::fs::remove_file(archive).unwrap();
+    }
+
+    #[test]
+    fn test_bundle_commands() {
+        match command(&["compress", "-i", "project", "-o", "project.cseqd", "--bundle"]) {
+            Command::Compress { directory, .. } => assert!(directory.bundle),
+            command => panic!("unexpected command {:?}", command),
+        }
+        assert_eq!(command(&["list", "-i", "project.cseqd"]), Command::List { input: "project.cseqd".into() });
+        assert_eq!(
+            command(&["extract-member", "-i", "project.cseqd", "reads/a.fq", "-o", "-", "--compressed"]),
+            Command::ExtractMember {
+                input: "project.cseqd".into(),
+                member: "reads/a.fq".into(),
+                output: "-".into(),
+                compressed: true,
+                mode: None,
+                line_width: None,
+            }
+        );
+        assert!(Opt::from_iter_safe(["compact_sequence", "extract-member", "-i", "b", "a.fa", "-o", "x", "--compressed", "--line-width", "60"]).is_err());
+    }
+}
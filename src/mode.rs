@@ -4,6 +4,7 @@ use std::str::FromStr;
 pub enum Mode {
     RNA,
     DNA,
+    Protein,
 }
 
 impl FromStr for Mode {
@@ -13,11 +14,31 @@ impl FromStr for Mode {
         match s.to_lowercase().as_str() {
             "rna" => Ok(Mode::RNA),
             "dna" => Ok(Mode::DNA),
+            "protein" => Ok(Mode::Protein),
             _ => Err("Invalid mode"),
         }
     }
 }
 
+impl Mode {
+    pub fn id(&self) -> u8 {
+        match self {
+            Mode::DNA => 0,
+            Mode::RNA => 1,
+            Mode::Protein => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Mode::DNA),
+            1 => Some(Mode::RNA),
+            2 => Some(Mode::Protein),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -26,6 +47,15 @@ mod tests {
     fn test_from_str() {
         assert_eq!(Mode::from_str("rna"), Ok(Mode::RNA));
         assert_eq!(Mode::from_str("dna"), Ok(Mode::DNA));
+        assert_eq!(Mode::from_str("protein"), Ok(Mode::Protein));
         assert_eq!(Mode::from_str("invalid"), Err("Invalid mode"));
     }
+
+    #[test]
+    fn test_id_round_trip() {
+        for mode in [Mode::DNA, Mode::RNA, Mode::Protein] {
+            assert_eq!(Mode::from_id(mode.id()), Some(mode));
+        }
+        assert_eq!(Mode::from_id(42), None);
+    }
 }
\ No newline at end of file
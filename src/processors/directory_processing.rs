@@ -1,67 +1,367 @@
-use std::fs;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::BufWriter;
 use std::path::{Path, PathBuf};
 use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use glob::{MatchOptions, Pattern};
 use walkdir::WalkDir;
 use rayon::prelude::*;
 use std::ffi::OsStr;
 
 use crate::Mode;
+use crate::bundle::{file_mode, is_bundle, Bundle, BundleWriter};
+use crate::errors::{DirectoryError, VerifyError};
+use crate::processors::processor::{DirectoryProcessor, Processor};
 
-pub fn compress_directory(input_dir: &str, output_dir: &str, mode: &Mode, supported_extensions: &[String]) -> Result<(), Box<dyn Error>> {
+// Output file names are built from a template where {stem}, {ext} and {name}
+// stand for the input file's stem, extension and full name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamingScheme {
+    template: String,
+}
+
+impl NamingScheme {
+    pub fn compressed() -> Self {
+        Self { template: "{stem}_output.{ext}".to_string() }
+    }
+
+    pub fn unpacked() -> Self {
+        Self { template: "{stem}_unpacked.{ext}".to_string() }
+    }
+
+    pub fn file_name(&self, file: &Path) -> String {
+        let part = |part: Option<&OsStr>| part.and_then(|part| part.to_str()).unwrap_or("").to_string();
+        self.template
+            .replace("{name}", &part(file.file_name()))
+            .replace("{stem}", &part(file.file_stem()))
+            .replace("{ext}", &part(file.extension()))
+    }
+}
+
+impl FromStr for NamingScheme {
+    type Err = DirectoryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !(s.contains("{stem}") || s.contains("{name}")) || s.contains('/') || s.contains('\\') {
+            return Err(DirectoryError::InvalidNaming(s.to_string()));
+        }
+        Ok(Self { template: s.to_string() })
+    }
+}
+
+// What to do with files that pass the filters but that no processor handles.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum UnsupportedFiles {
+    #[default]
+    Skip,
+    Warn,
+    Error,
+}
+
+impl FromStr for UnsupportedFiles {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(UnsupportedFiles::Skip),
+            "warn" => Ok(UnsupportedFiles::Warn),
+            "error" => Ok(UnsupportedFiles::Error),
+            _ => Err("Invalid unsupported files policy"),
+        }
+    }
+}
 
-    let input_path = Path::new(input_dir);
-    fs::create_dir_all(&output_dir)?;
+// Which files of a directory are walked. A pattern containing `/` is matched
+// against the path relative to the input directory, any other pattern against
+// the file name, so `*.fa` matches at every depth. Excluded directories are
+// not descended into.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub unsupported: UnsupportedFiles,
+}
+
+impl WalkOptions {
+    fn matches(pattern: &Pattern, relative: &Path) -> bool {
+        if pattern.as_str().contains('/') {
+            let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+            return pattern.matches_path_with(relative, options);
+        }
+        relative.file_name().is_some_and(|name| pattern.matches(&name.to_string_lossy()))
+    }
+
+    fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude.iter().any(|pattern| Self::matches(pattern, relative))
+    }
+
+    fn is_included(&self, relative: &Path) -> bool {
+        self.include.is_empty() || self.include.iter().any(|pattern| Self::matches(pattern, relative))
+    }
+}
+
+struct DirectoryFiles {
+    supported: Vec<PathBuf>,
+    unsupported: Vec<PathBuf>,
+}
+
+fn collect_files(input_dir: &str, directory: &DirectoryProcessor) -> Result<DirectoryFiles, DirectoryError> {
+    let options = directory.walk_options();
+    let relative = |path: &Path| path.strip_prefix(input_dir).unwrap_or(path).to_owned();
+    let mut walker = WalkDir::new(Path::new(input_dir)).follow_links(options.follow_symlinks);
+    if let Some(max_depth) = options.max_depth {
+        walker = walker.max_depth(max_depth);
+    }
 
-    let files: Vec<PathBuf> = WalkDir::new(input_path)
+    let (supported, unsupported): (Vec<PathBuf>, Vec<PathBuf>) = walker
         .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !options.is_excluded(&relative(entry.path())))
         .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry.file_type().is_file() && supported_extensions.iter().any(|ext| entry.path().extension() == Some(OsStr::new(ext)))
-        })
+        .filter(|entry| entry.file_type().is_file() && options.is_included(&relative(entry.path())))
         .map(|entry| entry.path().to_owned())
-        .collect();
+        .filter(|path| !is_index_file(path))
+        .partition(|path| directory.supported_extensions().iter().any(|ext| path.extension() == Some(OsStr::new(ext))));
+
+    if let (UnsupportedFiles::Error, Some(file)) = (options.unsupported, unsupported.first()) {
+        return Err(DirectoryError::UnsupportedFile(file.clone()));
+    }
+    Ok(DirectoryFiles { supported, unsupported })
+}
+
+// The `.fai` and `.cfi` files written next to a compressed FASTA belong to
+// that archive and are not inputs of their own.
+fn is_index_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(OsStr::to_str), Some("fai" | "cfi")) && path.with_extension("").is_file()
+}
 
-    files.par_iter().for_each(|file| {
-        let file_stem = file.file_stem().and_then(|f| f.to_str()).unwrap_or("output");
-        let format = file.extension().and_then(|ext| ext.to_str()).unwrap_or("txt");
-        let output_file_path = Path::new(&output_dir).join(format!("{}_output.{}", file_stem, format));
-        if let Err(err) = crate::compress_to_file(file.to_str().unwrap(), output_file_path.to_str().unwrap(), mode) {
-            eprintln!("Error processing file: {}: {}", file.display(), err);
+// Unsupported files only show up in the report under the warn policy.
+fn add_unsupported(mut report: DirectoryReport, files: DirectoryFiles, policy: UnsupportedFiles) -> DirectoryReport {
+    if policy == UnsupportedFiles::Warn {
+        report.skipped.extend(files.unsupported.into_iter().map(|file| (file, "unsupported file type".to_string())));
+    }
+    report
+}
+
+// Mirrors each input's path relative to the input directory under the output
+// directory. Two inputs mapping to the same output, or an output replacing an
+// input, is an error rather than a silent overwrite.
+fn plan_outputs(input_dir: &str, output_dir: &str, files: &[PathBuf], naming: &NamingScheme) -> Result<Vec<PathBuf>, DirectoryError> {
+    let inputs: HashSet<&PathBuf> = files.iter().collect();
+    let mut planned: HashMap<PathBuf, &PathBuf> = HashMap::new();
+    let mut outputs = Vec::new();
+    for file in files {
+        let relative = file.strip_prefix(input_dir).unwrap_or(file);
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        let output = Path::new(output_dir).join(parent).join(naming.file_name(file));
+        if inputs.contains(&output) {
+            return Err(DirectoryError::OverwritesInput(output));
         }
-    });
+        if let Some(first) = planned.insert(output.clone(), file) {
+            return Err(DirectoryError::OutputCollision { output, first: first.clone(), second: file.clone() });
+        }
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
 
-    Ok(())
+fn create_output_dirs(outputs: &[PathBuf]) -> std::io::Result<()> {
+    let parents: HashSet<&Path> = outputs.iter().filter_map(|output| output.parent()).collect();
+    parents.into_iter().try_for_each(fs::create_dir_all)
+}
+
+// Each file goes through the processor matching its own extension, so a
+// directory can mix text, FASTA and FASTQ files.
+fn file_processor(directory: &DirectoryProcessor, file: &Path) -> Result<Box<dyn Processor>, Box<dyn Error>> {
+    directory.processor_for(file).ok_or_else(|| format!("no processor handles {}", file.display()).into())
 }
 
-pub fn unpack_directory(input_dir: &str, output_dir: &str, mode: &Mode, supported_extensions: &[String]) -> Result<(), Box<dyn Error>> {
+// What happened to each file of a directory run. Files are skipped when a run
+// stops at the first failure, or when no processor handles them and
+// unsupported files are reported.
+#[derive(Debug, Default)]
+pub struct DirectoryReport {
+    pub succeeded: Vec<PathBuf>,
+    pub skipped: Vec<(PathBuf, String)>,
+    pub failed: Vec<(PathBuf, String)>,
+}
 
-    let input_path = Path::new(input_dir);
-    std::fs::create_dir_all(&output_dir)?;
+impl DirectoryReport {
+    pub fn total(&self) -> usize {
+        self.succeeded.len() + self.skipped.len() + self.failed.len()
+    }
 
-    let files: Vec<PathBuf> = WalkDir::new(input_path)
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry.file_type().is_file() && supported_extensions.iter().any(|ext| entry.path().extension() == Some(OsStr::new(ext)))
+    pub fn has_failures(&self) -> bool {
+        !self.failed.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<Self, DirectoryError> {
+        match self.has_failures() {
+            true => Err(DirectoryError::FilesFailed { failed: self.failed.len(), total: self.total() }),
+            false => Ok(self),
+        }
+    }
+}
+
+impl fmt::Display for DirectoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} succeeded, {} skipped, {} failed", self.succeeded.len(), self.skipped.len(), self.failed.len())?;
+        for (file, reason) in &self.failed {
+            writeln!(f, "  failed: {}: {}", file.display(), reason)?;
+        }
+        for (file, reason) in &self.skipped {
+            writeln!(f, "  skipped: {}: {}", file.display(), reason)?;
+        }
+        Ok(())
+    }
+}
+
+// Runs `job` on every file in parallel and collects the outcomes in file
+// order. With fail_fast, files not yet started when one fails are skipped.
+fn run_files<F>(files: &[PathBuf], fail_fast: bool, job: F) -> DirectoryReport
+where
+    F: Fn(usize, &Path) -> Result<(), Box<dyn Error>> + Sync,
+{
+    let stop = AtomicBool::new(false);
+    let outcomes: Vec<Option<Result<(), String>>> = files.par_iter()
+        .enumerate()
+        .map(|(i, file)| {
+            if fail_fast && stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            let result = job(i, file).map_err(|err| err.to_string());
+            if result.is_err() {
+                stop.store(true, Ordering::Relaxed);
+            }
+            Some(result)
         })
-        .map(|entry| entry.path().to_owned())
         .collect();
 
-    files.par_iter().for_each(|file| {
-        let file_stem = file.file_stem().and_then(|f| f.to_str()).unwrap_or("output");
-        let format = file.extension().and_then(|ext| ext.to_str()).unwrap_or("txt");
-        let output_file_path = Path::new(&output_dir).join(format!("{}_unpacked.{}", file_stem, format));
-        if let Err(err) = crate::unpack_from_file(file.to_str().unwrap(), output_file_path.to_str().unwrap(), mode) {
-            eprintln!("Error processing file: {}: {}", file.display(), err);
+    let mut report = DirectoryReport::default();
+    for (file, outcome) in files.iter().zip(outcomes) {
+        match outcome {
+            Some(Ok(())) => report.succeeded.push(file.clone()),
+            Some(Err(reason)) => report.failed.push((file.clone(), reason)),
+            None => report.skipped.push((file.clone(), "not started after an earlier failure".to_string())),
         }
+    }
+    report
+}
+
+pub fn compress_directory(directory: &DirectoryProcessor, input_dir: &str, output_dir: &str, mode: &Mode) -> Result<DirectoryReport, Box<dyn Error>> {
+    if !Path::new(input_dir).is_dir() {
+        return Err(format!("{} is not a directory", input_dir).into());
+    }
+    if directory.bundle() {
+        return compress_bundle(directory, input_dir, output_dir, mode);
+    }
+    fs::create_dir_all(output_dir)?;
+    let files = collect_files(input_dir, directory)?;
+    let naming = directory.naming().cloned().unwrap_or_else(NamingScheme::compressed);
+    let outputs = plan_outputs(input_dir, output_dir, &files.supported, &naming)?;
+    create_output_dirs(&outputs)?;
+
+    let report = run_files(&files.supported, directory.fail_fast(), |i, file| {
+        file_processor(directory, file)?.compress(file.to_str().unwrap(), outputs[i].to_str().unwrap(), mode)
+    });
+    Ok(add_unsupported(report, files, directory.walk_options().unsupported))
+}
+
+pub fn unpack_directory(directory: &DirectoryProcessor, input_dir: &str, output_dir: &str, mode: Option<&Mode>) -> Result<DirectoryReport, Box<dyn Error>> {
+    if is_bundle(input_dir) {
+        return unpack_bundle(directory, input_dir, output_dir, mode);
+    }
+    fs::create_dir_all(output_dir)?;
+    let files = collect_files(input_dir, directory)?;
+    let naming = directory.naming().cloned().unwrap_or_else(NamingScheme::unpacked);
+    let outputs = plan_outputs(input_dir, output_dir, &files.supported, &naming)?;
+    create_output_dirs(&outputs)?;
+
+    let report = run_files(&files.supported, directory.fail_fast(), |i, file| {
+        file_processor(directory, file)?.unpack(file.to_str().unwrap(), outputs[i].to_str().unwrap(), mode)
     });
+    Ok(add_unsupported(report, files, directory.walk_options().unsupported))
+}
 
+// Archives are matched to originals by name, so comparing a whole directory
+// against its originals is not supported; stored checksums are checked instead.
+pub fn verify_directory(directory: &DirectoryProcessor, input_dir: &str, original: Option<&str>, mode: Option<&Mode>) -> Result<DirectoryReport, Box<dyn Error>> {
+    if original.is_some() {
+        return Err(VerifyError::Unsupported("directories are verified against their stored checksums only".to_string()).into());
+    }
+
+    if is_bundle(input_dir) {
+        let bundle = Bundle::open(input_dir)?;
+        let members: Vec<PathBuf> = bundle.entries().iter().map(|entry| PathBuf::from(&entry.path)).collect();
+        return Ok(run_files(&members, directory.fail_fast(), |i, _| bundle.verify_member(&bundle.entries()[i], mode)));
+    }
+
+    let files = collect_files(input_dir, directory)?;
+    let report = run_files(&files.supported, directory.fail_fast(), |_, file| {
+        file_processor(directory, file)?.verify(file.to_str().unwrap(), None, mode)
+    });
+    Ok(add_unsupported(report, files, directory.walk_options().unsupported))
+}
+
+// Compresses every file into a fresh staging directory next to the bundle,
+// then packs the ones that succeeded under their path relative to the input
+// directory. The staging directory is removed either way.
+fn compress_bundle(directory: &DirectoryProcessor, input_dir: &str, bundle_path: &str, mode: &Mode) -> Result<DirectoryReport, Box<dyn Error>> {
+    let files = collect_files(input_dir, directory)?;
+    let bundle_dir = match Path::new(bundle_path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(bundle_dir)?;
+    let staging_dir = tempfile::Builder::new().prefix(".cseqd-staging-").tempdir_in(bundle_dir)?;
+    let naming = NamingScheme { template: "{name}".to_string() };
+    let outputs = plan_outputs(input_dir, &staging_dir.path().to_string_lossy(), &files.supported, &naming)?;
+    create_output_dirs(&outputs)?;
+
+    let report = run_files(&files.supported, directory.fail_fast(), |i, file| {
+        file_processor(directory, file)?.compress(file.to_str().unwrap(), outputs[i].to_str().unwrap(), mode)
+    });
+    let packed = write_bundle(input_dir, bundle_path, mode, &report, &files.supported, &outputs);
+    staging_dir.close()?;
+    packed?;
+    Ok(add_unsupported(report, files, directory.walk_options().unsupported))
+}
+
+fn write_bundle(input_dir: &str, bundle_path: &str, mode: &Mode, report: &DirectoryReport, files: &[PathBuf], outputs: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    let succeeded: HashSet<&PathBuf> = report.succeeded.iter().collect();
+    let mut bundle = BundleWriter::new(BufWriter::new(File::create(bundle_path)?))?;
+    for (file, output) in files.iter().zip(outputs).filter(|(file, _)| succeeded.contains(file)) {
+        let relative = file.strip_prefix(input_dir).unwrap_or(file);
+        let member_path: Vec<String> = relative.components().map(|part| part.as_os_str().to_string_lossy().into_owned()).collect();
+        let metadata = fs::metadata(file)?;
+        bundle.add_member(&member_path.join("/"), mode, file_mode(&metadata), metadata.len(), &mut File::open(output)?)?;
+    }
+    bundle.finish()?;
     Ok(())
 }
 
+// Unpacks every member of a bundle under the output directory at its stored
+// path.
+fn unpack_bundle(directory: &DirectoryProcessor, bundle_path: &str, output_dir: &str, mode: Option<&Mode>) -> Result<DirectoryReport, Box<dyn Error>> {
+    let bundle = Bundle::open(bundle_path)?;
+    let members: Vec<PathBuf> = bundle.entries().iter().map(|entry| PathBuf::from(&entry.path)).collect();
+    let outputs: Vec<PathBuf> = members.iter().map(|member| Path::new(output_dir).join(member)).collect();
+    fs::create_dir_all(output_dir)?;
+    create_output_dirs(&outputs)?;
+
+    Ok(run_files(&members, directory.fail_fast(), |i, _| {
+        bundle.unpack_member(&bundle.entries()[i], outputs[i].to_str().unwrap(), mode, directory.line_width())
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::file_extensions::supported_extensions;
     use std::fs::File;
     use std::io::Write;
     
@@ -71,7 +371,7 @@ mod tests {
     #[test]
     fn test_dna_compress_and_unpack_directory() -> Result<(), Box<dyn Error>> {
         const TEST_MODE: Mode = Mode::DNA;
-        let supported_extensions = vec!["txt".to_string()];
+        let directory = DirectoryProcessor::new(vec!["txt".to_string()], "ascii");
         let temp_input_dir = tempfile::tempdir()?;
         let input_dir_str = temp_input_dir.path().to_str().unwrap();
 
@@ -82,17 +382,28 @@ mod tests {
         let mut file = File::create(&file_path)?;
         writeln!(file, "{}", SAMPLE_DNA_SEQUENCE)?;
 
-        compress_directory(input_dir_str, &temp_output_dir_str, &TEST_MODE, &supported_extensions)?;
+        compress_directory(&directory, input_dir_str, &temp_output_dir_str, &TEST_MODE)?.into_result()?;
 
         assert!(Path::new(&temp_output_dir_str).join("sample_output.txt").exists());
 
-        unpack_directory(&temp_output_dir_str, &temp_unpacked_dir_str, &TEST_MODE, &supported_extensions)?;
+        unpack_directory(&directory, &temp_output_dir_str, &temp_unpacked_dir_str, None)?.into_result()?;
 
         let unpacked_file_path = Path::new(&temp_unpacked_dir_str).join("sample_output_unpacked.txt");
         assert!(unpacked_file_path.exists());
         let unpacked_content = std::fs::read_to_string(unpacked_file_path)?;
         assert_eq!(unpacked_content.trim(), SAMPLE_DNA_SEQUENCE);
 
+        verify_directory(&directory, &temp_output_dir_str, None, None)?.into_result()?;
+        assert!(verify_directory(&directory, &temp_output_dir_str, Some(input_dir_str), None).is_err());
+
+        let compressed_file_path = Path::new(&temp_output_dir_str).join("sample_output.txt");
+        let mut compressed = std::fs::read(&compressed_file_path)?;
+        compressed.truncate(compressed.len() - 1);
+        std::fs::write(&compressed_file_path, compressed)?;
+        let report = verify_directory(&directory, &temp_output_dir_str, None, None)?;
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.into_result().is_err());
+
         Ok(())
     }
 
@@ -100,7 +411,7 @@ mod tests {
     #[test]
     fn test_rna_compress_and_unpack_directory() -> Result<(), Box<dyn Error>> {
         const TEST_MODE: Mode = Mode::RNA;
-        let supported_extensions = vec!["txt".to_string()];
+        let directory = DirectoryProcessor::new(vec!["txt".to_string()], "ascii");
         let temp_input_dir = tempfile::tempdir()?;
         let input_dir_str = temp_input_dir.path().to_str().unwrap();
 
@@ -111,11 +422,11 @@ mod tests {
         let mut file = File::create(&file_path)?;
         writeln!(file, "{}", SAMPLE_RNA_SEQUENCE)?;
 
-        compress_directory(input_dir_str, &temp_output_dir_str, &TEST_MODE, &supported_extensions)?;
+        compress_directory(&directory, input_dir_str, &temp_output_dir_str, &TEST_MODE)?.into_result()?;
 
         assert!(Path::new(&temp_output_dir_str).join("sample_output.txt").exists());
 
-        unpack_directory(&temp_output_dir_str, &temp_unpacked_dir_str, &TEST_MODE, &supported_extensions)?;
+        unpack_directory(&directory, &temp_output_dir_str, &temp_unpacked_dir_str, None)?.into_result()?;
 
         let unpacked_file_path = Path::new(&temp_unpacked_dir_str).join("sample_output_unpacked.txt");
         assert!(unpacked_file_path.exists());
@@ -125,4 +436,227 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_mixed_directory() -> Result<(), Box<dyn Error>> {
+        let directory = DirectoryProcessor::new(supported_extensions(), "ascii");
+        let temp_input_dir = tempfile::tempdir()?;
+        let input_dir_str = temp_input_dir.path().to_str().unwrap();
+        let temp_output_dir_str = format!("{}_outputs", input_dir_str);
+        let temp_unpacked_dir_str = format!("{}_unpacked_outputs", input_dir_str);
+
+        let fasta = ">seq1 chromosome\nACGTNNAC\nGT\n";
+        let fastq = "@read1\nACGTN\n+\nIII#F\n";
+        std::fs::write(temp_input_dir.path().join("sample.txt"), format!("{}\n", SAMPLE_DNA_SEQUENCE))?;
+        std::fs::write(temp_input_dir.path().join("genome.fa"), fasta)?;
+        std::fs::write(temp_input_dir.path().join("reads.fq"), fastq)?;
+        std::fs::write(temp_input_dir.path().join("notes.md"), "not a sequence\n")?;
+
+        let report = compress_directory(&directory, input_dir_str, &temp_output_dir_str, &Mode::DNA)?;
+        assert_eq!(report.succeeded.len(), 3);
+        let output_dir = Path::new(&temp_output_dir_str);
+        assert!(!output_dir.join("notes_output.md").exists());
+        assert!(output_dir.join("genome_output.fa.fai").exists());
+        assert!(output_dir.join("genome_output.fa.cfi").exists());
+
+        // The index files next to the archive are not reported as unsupported.
+        let directory = DirectoryProcessor::new(supported_extensions(), "ascii").with_unsupported_files(UnsupportedFiles::Error);
+        verify_directory(&directory, &temp_output_dir_str, None, None)?.into_result()?;
+        let report = unpack_directory(&directory, &temp_output_dir_str, &temp_unpacked_dir_str, None)?;
+        assert!(report.skipped.is_empty());
+        report.into_result()?;
+        let unpacked_dir = Path::new(&temp_unpacked_dir_str);
+        assert_eq!(std::fs::read_to_string(unpacked_dir.join("genome_output_unpacked.fa"))?, fasta);
+        assert_eq!(std::fs::read_to_string(unpacked_dir.join("reads_output_unpacked.fq"))?, fastq);
+        assert_eq!(std::fs::read_to_string(unpacked_dir.join("sample_output_unpacked.txt"))?.trim(), SAMPLE_DNA_SEQUENCE);
+
+        std::fs::remove_dir_all(&temp_output_dir_str)?;
+        std::fs::remove_dir_all(&temp_unpacked_dir_str)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_naming_scheme() {
+        let file = Path::new("data/sample.fa");
+        assert_eq!(NamingScheme::compressed().file_name(file), "sample_output.fa");
+        assert_eq!(NamingScheme::unpacked().file_name(file), "sample_unpacked.fa");
+        assert_eq!("{name}.cseq".parse::<NamingScheme>().unwrap().file_name(file), "sample.fa.cseq");
+        assert_eq!("{stem}.{ext}".parse::<NamingScheme>().unwrap().file_name(file), "sample.fa");
+        assert!("output.{ext}".parse::<NamingScheme>().is_err());
+        assert!("out/{name}".parse::<NamingScheme>().is_err());
+    }
+
+    #[test]
+    fn test_mirrored_output_tree() -> Result<(), Box<dyn Error>> {
+        let temp_input_dir = tempfile::tempdir()?;
+        let input_dir_str = temp_input_dir.path().to_str().unwrap();
+        let temp_output_dir_str = format!("{}_outputs", input_dir_str);
+        let temp_unpacked_dir_str = format!("{}_unpacked_outputs", input_dir_str);
+        for (dir, sequence) in [("a", SAMPLE_DNA_SEQUENCE), ("b/c", "ACGT")] {
+            fs::create_dir_all(temp_input_dir.path().join(dir))?;
+            fs::write(temp_input_dir.path().join(dir).join("sample.txt"), format!("{}\n", sequence))?;
+        }
+
+        let directory = DirectoryProcessor::new(vec!["txt".to_string()], "ascii");
+        compress_directory(&directory, input_dir_str, &temp_output_dir_str, &Mode::DNA)?.into_result()?;
+        assert!(Path::new(&temp_output_dir_str).join("a/sample_output.txt").exists());
+        assert!(Path::new(&temp_output_dir_str).join("b/c/sample_output.txt").exists());
+
+        let directory = directory.with_naming(Some("{name}".parse()?));
+        unpack_directory(&directory, &temp_output_dir_str, &temp_unpacked_dir_str, None)?.into_result()?;
+        assert_eq!(fs::read_to_string(Path::new(&temp_unpacked_dir_str).join("a/sample_output.txt"))?.trim(), SAMPLE_DNA_SEQUENCE);
+        assert_eq!(fs::read_to_string(Path::new(&temp_unpacked_dir_str).join("b/c/sample_output.txt"))?.trim(), "ACGT");
+
+        let err = compress_directory(&directory, input_dir_str, input_dir_str, &Mode::DNA).unwrap_err();
+        assert!(matches!(err.downcast_ref::<DirectoryError>(), Some(DirectoryError::OverwritesInput(_))));
+
+        fs::write(temp_input_dir.path().join("a/sample.fa"), ">seq\nACGT\n")?;
+        let directory = DirectoryProcessor::new(supported_extensions(), "ascii").with_naming(Some("{stem}.cseq".parse()?));
+        let err = compress_directory(&directory, input_dir_str, &temp_output_dir_str, &Mode::DNA).unwrap_err();
+        assert!(matches!(err.downcast_ref::<DirectoryError>(), Some(DirectoryError::OutputCollision { .. })));
+
+        fs::remove_dir_all(&temp_output_dir_str)?;
+        fs::remove_dir_all(&temp_unpacked_dir_str)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory_report() {
+        let files: Vec<PathBuf> = ["a.txt", "b.txt", "c.txt"].iter().map(PathBuf::from).collect();
+        let job = |i: usize, _: &Path| -> Result<(), Box<dyn Error>> {
+            match i {
+                1 => Err("corrupt".into()),
+                _ => Ok(()),
+            }
+        };
+
+        let report = run_files(&files, false, job);
+        assert_eq!(report.succeeded, vec![PathBuf::from("a.txt"), PathBuf::from("c.txt")]);
+        assert_eq!(report.failed, vec![(PathBuf::from("b.txt"), "corrupt".to_string())]);
+        assert_eq!(report.to_string(), "2 succeeded, 0 skipped, 1 failed\n  failed: b.txt: corrupt\n");
+        assert!(matches!(report.into_result(), Err(DirectoryError::FilesFailed { failed: 1, total: 3 })));
+
+        // A single thread works through the files in order.
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let report = pool.install(|| run_files(&files, true, job));
+        assert_eq!(report.succeeded.len(), 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.skipped[0].0, PathBuf::from("c.txt"));
+        assert!(run_files(&files[..1], true, job).into_result().is_ok());
+    }
+
+    #[test]
+    fn test_walk_filters() -> Result<(), Box<dyn Error>> {
+        let temp_input_dir = tempfile::tempdir()?;
+        let root = temp_input_dir.path();
+        let input_dir_str = root.to_str().unwrap();
+        for file in ["top.txt", "sub/a.fa", "sub/deep/b.txt", "tmp/c.txt", "notes.md"] {
+            fs::create_dir_all(root.join(file).parent().unwrap())?;
+            fs::write(root.join(file), "ACGT\n")?;
+        }
+        let relative_files = |directory: &DirectoryProcessor| -> Result<Vec<String>, DirectoryError> {
+            let mut files: Vec<String> = collect_files(input_dir_str, directory)?.supported.iter()
+                .map(|file| file.strip_prefix(root).unwrap().to_string_lossy().into_owned())
+                .collect();
+            files.sort();
+            Ok(files)
+        };
+        let directory = || DirectoryProcessor::new(supported_extensions(), "ascii");
+
+        assert_eq!(relative_files(&directory())?, vec!["sub/a.fa", "sub/deep/b.txt", "tmp/c.txt", "top.txt"]);
+        assert_eq!(relative_files(&directory().with_exclude(vec![Pattern::new("tmp")?]))?, vec!["sub/a.fa", "sub/deep/b.txt", "top.txt"]);
+        assert_eq!(relative_files(&directory().with_include(vec![Pattern::new("*.txt")?]))?, vec!["sub/deep/b.txt", "tmp/c.txt", "top.txt"]);
+        assert_eq!(relative_files(&directory().with_include(vec![Pattern::new("sub/*")?]))?, vec!["sub/a.fa"]);
+        assert_eq!(relative_files(&directory().with_max_depth(Some(1)))?, vec!["top.txt"]);
+        assert_eq!(relative_files(&directory().with_max_depth(Some(2)))?, vec!["sub/a.fa", "tmp/c.txt", "top.txt"]);
+
+        let err = collect_files(input_dir_str, &directory().with_unsupported_files(UnsupportedFiles::Error)).err();
+        assert!(matches!(err, Some(DirectoryError::UnsupportedFile(file)) if file == root.join("notes.md")));
+
+        let temp_output_dir_str = format!("{}_outputs", input_dir_str);
+        let warn = directory().with_unsupported_files(UnsupportedFiles::Warn).with_include(vec![Pattern::new("top.*")?, Pattern::new("*.md")?]);
+        let report = compress_directory(&warn, input_dir_str, &temp_output_dir_str, &Mode::DNA)?;
+        assert_eq!(report.succeeded, vec![root.join("top.txt")]);
+        assert_eq!(report.skipped, vec![(root.join("notes.md"), "unsupported file type".to_string())]);
+        assert!(!report.has_failures());
+        let report = compress_directory(&warn.with_unsupported_files(UnsupportedFiles::Skip), input_dir_str, &temp_output_dir_str, &Mode::DNA)?;
+        assert!(report.skipped.is_empty());
+
+        #[cfg(unix)]
+        {
+            let linked_dir = tempfile::tempdir()?;
+            fs::write(linked_dir.path().join("linked.txt"), "ACGT\n")?;
+            std::os::unix::fs::symlink(linked_dir.path(), root.join("link"))?;
+            assert!(!relative_files(&directory())?.contains(&"link/linked.txt".to_string()));
+            assert!(relative_files(&directory().with_follow_symlinks(true))?.contains(&"link/linked.txt".to_string()));
+        }
+
+        fs::remove_dir_all(&temp_output_dir_str)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory_bundle() -> Result<(), Box<dyn Error>> {
+        let temp_input_dir = tempfile::tempdir()?;
+        let root = temp_input_dir.path();
+        let input_dir_str = root.to_str().unwrap();
+        let bundle_path = format!("{}.cseqd", input_dir_str);
+        let temp_unpacked_dir_str = format!("{}_unpacked_outputs", input_dir_str);
+        let fasta = ">seq1\nACGTNNAC\nGT\n";
+        fs::create_dir_all(root.join("a/b"))?;
+        fs::write(root.join("sample.txt"), format!("{}\n", SAMPLE_DNA_SEQUENCE))?;
+        fs::write(root.join("a/b/genome.fa"), fasta)?;
+        fs::write(root.join("a/notes.md"), "not a sequence\n")?;
+
+        let directory = DirectoryProcessor::new(supported_extensions(), "ascii").with_bundle(true);
+        let report = compress_directory(&directory, input_dir_str, &bundle_path, &Mode::DNA)?.into_result()?;
+        assert_eq!(report.succeeded.len(), 2);
+
+        let bundle = Bundle::open(&bundle_path)?;
+        let paths: Vec<&str> = bundle.entries().iter().map(|entry| entry.path.as_str()).collect();
+        assert!(paths.contains(&"a/b/genome.fa") && paths.contains(&"sample.txt") && paths.len() == 2);
+        let genome = bundle.find("a/b/genome.fa")?;
+        assert_eq!((&genome.mode, genome.original_len), (&Mode::DNA, fasta.len() as u64));
+        assert_eq!(genome.permissions, file_mode(&fs::metadata(root.join("a/b/genome.fa"))?));
+        verify_directory(&directory, &bundle_path, None, None)?.into_result()?;
+
+        unpack_directory(&directory, &bundle_path, &temp_unpacked_dir_str, None)?.into_result()?;
+        let unpacked_dir = Path::new(&temp_unpacked_dir_str);
+        assert_eq!(fs::read_to_string(unpacked_dir.join("a/b/genome.fa"))?, fasta);
+        assert_eq!(fs::read_to_string(unpacked_dir.join("sample.txt"))?.trim(), SAMPLE_DNA_SEQUENCE);
+        assert_eq!(file_mode(&fs::metadata(unpacked_dir.join("sample.txt"))?), file_mode(&fs::metadata(root.join("sample.txt"))?));
+
+        let member = unpacked_dir.join("genome.fa");
+        bundle.unpack_member(bundle.find("a/b/genome.fa")?, member.to_str().unwrap(), None, Some(4))?;
+        assert_eq!(fs::read_to_string(&member)?, ">seq1\nACGT\nNNAC\nGT\n");
+
+        fs::remove_file(&bundle_path)?;
+        fs::remove_dir_all(&temp_unpacked_dir_str)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_staging() -> Result<(), Box<dyn Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let input_dir = temp_dir.path().join("input");
+        let bundle_path = temp_dir.path().join("out/data.cseqd");
+        let parts_dir = temp_dir.path().join("out/data.cseqd.parts");
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&parts_dir)?;
+        fs::write(parts_dir.join("keep.txt"), "user data\n")?;
+
+        let directory = DirectoryProcessor::new(supported_extensions(), "ascii").with_bundle(true);
+        let (input_dir_str, bundle_path_str) = (input_dir.to_str().unwrap(), bundle_path.to_str().unwrap());
+        let report = compress_directory(&directory, input_dir_str, bundle_path_str, &Mode::DNA)?.into_result()?;
+        assert!(report.succeeded.is_empty());
+        assert!(Bundle::open(bundle_path_str)?.entries().is_empty());
+
+        fs::write(input_dir.join("sample.txt"), format!("{}\n", SAMPLE_DNA_SEQUENCE))?;
+        compress_directory(&directory, input_dir_str, bundle_path_str, &Mode::DNA)?.into_result()?;
+        assert_eq!(Bundle::open(bundle_path_str)?.entries().len(), 1);
+        assert_eq!(fs::read_to_string(parts_dir.join("keep.txt"))?, "user data\n");
+        let mut leftovers: Vec<_> = fs::read_dir(temp_dir.path().join("out"))?.map(|entry| entry.map(|entry| entry.file_name())).collect::<Result<_, _>>()?;
+        leftovers.sort();
+        assert_eq!(leftovers, ["data.cseqd", "data.cseqd.parts"]);
+        Ok(())
+    }
 }
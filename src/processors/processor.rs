@@ -1,61 +1,265 @@
 use crate::Mode;
+use crate::codec::DEFAULT_CODEC;
+use crate::quality::QualityBinning;
 use crate::{
     compress_to_file, 
     unpack_from_file,
     compress_fasta_to_file,
     unpack_fasta_from_file,
+    compress_fastq_to_file,
+    unpack_fastq_from_file,
+    write_fasta_index_files,
+    verify_file,
 };
-use crate::processors::directory_processing::{compress_directory, unpack_directory};
+use crate::container::SourceFormat;
+use crate::index::SequenceIndex;
+use crate::stdio::is_stdio;
+use crate::file_extensions::format_for_extension;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use glob::Pattern;
+use crate::processors::directory_processing::{compress_directory, unpack_directory, verify_directory, NamingScheme, UnsupportedFiles, WalkOptions};
 
 pub trait Processor {
     fn compress(&self, input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>>;
-    fn unpack(&self, input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>>;
+    fn unpack(&self, input: &str, output_file_name: &str, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>>;
+    fn verify(&self, input: &str, original: Option<&str>, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>>;
 }
 
-pub struct TextProcessor;
+pub struct TextProcessor {
+    codec: String,
+}
+
+impl TextProcessor {
+    pub fn new(codec: &str) -> Self {
+        Self { codec: codec.to_string() }
+    }
+}
+
+impl Default for TextProcessor {
+    fn default() -> Self {
+        Self::new(DEFAULT_CODEC)
+    }
+}
 
 impl Processor for TextProcessor {
     fn compress(&self, input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
-        compress_to_file(input, output_file_name, mode)
+        compress_to_file(input, output_file_name, mode, &self.codec)
     }
 
-    fn unpack(&self, input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
+    fn unpack(&self, input: &str, output_file_name: &str, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
         unpack_from_file(input, output_file_name, mode)
     }
+
+    fn verify(&self, input: &str, original: Option<&str>, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+        verify_file(input, original, SourceFormat::Text, mode)
+    }
 }
 
 pub struct DirectoryProcessor {
     supported_extensions: Vec<String>,
+    codec: String,
+    quality_binning: Option<QualityBinning>,
+    line_width: Option<usize>,
+    naming: Option<NamingScheme>,
+    fail_fast: bool,
+    walk: WalkOptions,
+    bundle: bool,
 }
 
 impl DirectoryProcessor {
-    pub fn new(supported_extensions: Vec<String>) -> Self {
-        Self { supported_extensions }
+    pub fn new(supported_extensions: Vec<String>, codec: &str) -> Self {
+        Self { supported_extensions, codec: codec.to_string(), quality_binning: None, line_width: None, naming: None, fail_fast: false, walk: WalkOptions::default(), bundle: false }
+    }
+
+    // Passed on to the FASTQ files of the directory.
+    pub fn with_quality_binning(mut self, quality_binning: Option<QualityBinning>) -> Self {
+        self.quality_binning = quality_binning;
+        self
+    }
+
+    // Passed on to the FASTA files of the directory.
+    pub fn with_line_width(mut self, line_width: Option<usize>) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    // Overrides the default `{stem}_output.{ext}` and `{stem}_unpacked.{ext}` output names.
+    pub fn with_naming(mut self, naming: Option<NamingScheme>) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    // Stop starting new files after the first failure instead of processing them all.
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    // Only files matching one of these globs are processed; all files when empty.
+    pub fn with_include(mut self, include: Vec<Pattern>) -> Self {
+        self.walk.include = include;
+        self
+    }
+
+    pub fn with_exclude(mut self, exclude: Vec<Pattern>) -> Self {
+        self.walk.exclude = exclude;
+        self
+    }
+
+    // A depth of 1 only processes the files directly inside the directory.
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.walk.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.walk.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    pub fn with_unsupported_files(mut self, unsupported: UnsupportedFiles) -> Self {
+        self.walk.unsupported = unsupported;
+        self
+    }
+
+    // Pack the compressed files into a single bundle at the output path
+    // instead of writing a tree of them.
+    pub fn with_bundle(mut self, bundle: bool) -> Self {
+        self.bundle = bundle;
+        self
+    }
+
+    pub fn supported_extensions(&self) -> &[String] {
+        &self.supported_extensions
+    }
+
+    pub fn naming(&self) -> Option<&NamingScheme> {
+        self.naming.as_ref()
+    }
+
+    pub fn fail_fast(&self) -> bool {
+        self.fail_fast
+    }
+
+    pub fn walk_options(&self) -> &WalkOptions {
+        &self.walk
+    }
+
+    pub fn line_width(&self) -> Option<usize> {
+        self.line_width
+    }
+
+    pub fn bundle(&self) -> bool {
+        self.bundle
+    }
+
+    // Picks the processor for one file of the directory from its extension,
+    // None when no processor handles it.
+    pub fn processor_for(&self, path: &Path) -> Option<Box<dyn Processor>> {
+        let format = path.extension().and_then(|ext| ext.to_str()).and_then(format_for_extension)?;
+        let processor: Box<dyn Processor> = match format {
+            SourceFormat::Text => Box::new(TextProcessor::new(&self.codec)),
+            SourceFormat::Fasta => Box::new(FastaProcessor::new(&self.codec).with_line_width(self.line_width)),
+            SourceFormat::Fastq => Box::new(FastqProcessor::new(&self.codec).with_quality_binning(self.quality_binning.clone())),
+        };
+        Some(processor)
     }
 }
 
 impl Processor for DirectoryProcessor {
     fn compress(&self, input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
-        compress_directory(input, output_file_name, mode, &self.supported_extensions)
-            .map_err(|e| e.into())
+        compress_directory(self, input, output_file_name, mode)?.into_result()?;
+        Ok(())
     }
 
-    fn unpack(&self, input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
-        unpack_directory(input, output_file_name, mode, &self.supported_extensions)
-            .map_err(|e| e.into())
+    fn unpack(&self, input: &str, output_file_name: &str, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+        unpack_directory(self, input, output_file_name, mode)?.into_result()?;
+        Ok(())
     }
 
+    fn verify(&self, input: &str, original: Option<&str>, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+        verify_directory(self, input, original, mode)?.into_result()?;
+        Ok(())
+    }
+}
+
+pub struct FastaProcessor {
+    codec: String,
+    line_width: Option<usize>,
+}
+
+impl FastaProcessor {
+    pub fn new(codec: &str) -> Self {
+        Self { codec: codec.to_string(), line_width: None }
+    }
+
+    pub fn with_line_width(mut self, line_width: Option<usize>) -> Self {
+        self.line_width = line_width;
+        self
+    }
 }
 
-pub struct FastaProcessor;
+impl Default for FastaProcessor {
+    fn default() -> Self {
+        Self::new(DEFAULT_CODEC)
+    }
+}
 
 impl Processor for FastaProcessor {
     fn compress(&self, input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
-        compress_fasta_to_file(input, output_file_name, mode)
+        compress_fasta_to_file(input, output_file_name, mode, &self.codec)?;
+        // Companion index files need a path to sit next to.
+        if is_stdio(output_file_name) {
+            return Ok(());
+        }
+        let index = SequenceIndex::load(&mut BufReader::new(File::open(output_file_name)?))?;
+        write_fasta_index_files(output_file_name, &index)
     }
 
-    fn unpack(&self, input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
-        unpack_fasta_from_file(input, output_file_name, mode)
+    fn unpack(&self, input: &str, output_file_name: &str, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+        unpack_fasta_from_file(input, output_file_name, mode, self.line_width)
+    }
+
+    fn verify(&self, input: &str, original: Option<&str>, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+        verify_file(input, original, SourceFormat::Fasta, mode)
+    }
+}
+
+pub struct FastqProcessor {
+    codec: String,
+    quality_binning: Option<QualityBinning>,
+}
+
+impl FastqProcessor {
+    pub fn new(codec: &str) -> Self {
+        Self { codec: codec.to_string(), quality_binning: None }
+    }
+
+    pub fn with_quality_binning(mut self, quality_binning: Option<QualityBinning>) -> Self {
+        self.quality_binning = quality_binning;
+        self
+    }
+}
+
+impl Default for FastqProcessor {
+    fn default() -> Self {
+        Self::new(DEFAULT_CODEC)
+    }
+}
+
+impl Processor for FastqProcessor {
+    fn compress(&self, input: &str, output_file_name: &str, mode: &Mode) -> Result<(), Box<dyn std::error::Error>> {
+        compress_fastq_to_file(input, output_file_name, mode, &self.codec, self.quality_binning.as_ref())
+    }
+
+    fn unpack(&self, input: &str, output_file_name: &str, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+        unpack_fastq_from_file(input, output_file_name, mode)
+    }
+
+    fn verify(&self, input: &str, original: Option<&str>, mode: Option<&Mode>) -> Result<(), Box<dyn std::error::Error>> {
+        verify_file(input, original, SourceFormat::Fastq, mode)
     }
 }
 
@@ -63,7 +267,11 @@ impl Processor for FastaProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::container::{Header, Record, RecordKind};
+    use crate::errors::VerifyError;
+    use crate::tests::write_test_archive;
     use std::fs::File;
+    use crate::fasta_index_paths;
     use std::io::{Read, Write};
 
     #[test]
@@ -75,14 +283,14 @@ mod tests {
         let mut file = File::create(input_path).unwrap();
         file.write_all(test_sequence).unwrap();
 
-        let processor = TextProcessor;
+        let processor = TextProcessor::default();
         let mode = Mode::DNA;
         processor.compress(input_path, output_path, &mode).unwrap();
 
         let mut file = File::open(output_path).unwrap();
-        let mut content = String::new();
-        file.read_to_string(&mut content).unwrap();
-        assert!(content.trim().len() < test_sequence.len());
+        Header::read_from(&mut file).unwrap();
+        let record = Record::read_from(&mut file).unwrap().unwrap();
+        assert!(record.payload.len() < test_sequence.len());
 
         std::fs::remove_file(input_path).unwrap();
         std::fs::remove_file(output_path).unwrap();
@@ -94,12 +302,12 @@ mod tests {
         let output_path = "test_dna_unpack_output.txt";
         let test_sequence = b"random_ascii_sequence";
 
-        let mut file = File::create(input_path).unwrap();
-        file.write_all(test_sequence).unwrap();
-
-        let processor = TextProcessor;
         let mode = Mode::DNA;
-        processor.unpack(input_path, output_path, &mode).unwrap();
+        let header = Header::new(&mode, DEFAULT_CODEC, SourceFormat::Text);
+        write_test_archive(input_path, &header, &[Record::new(RecordKind::Sequence, test_sequence.to_vec())]);
+
+        let processor = TextProcessor::default();
+        processor.unpack(input_path, output_path, Some(&mode)).unwrap();
 
         let mut file = File::open(output_path).unwrap();
         let mut content = String::new();
@@ -119,14 +327,14 @@ mod tests {
         let mut file = File::create(input_path).unwrap();
         file.write_all(test_sequence).unwrap();
 
-        let processor = TextProcessor;
+        let processor = TextProcessor::default();
         let mode = Mode::RNA;
         processor.compress(input_path, output_path, &mode).unwrap();
 
         let mut file = File::open(output_path).unwrap();
-        let mut content = String::new();
-        file.read_to_string(&mut content).unwrap();
-        assert!(content.trim().len() < test_sequence.len());
+        Header::read_from(&mut file).unwrap();
+        let record = Record::read_from(&mut file).unwrap().unwrap();
+        assert!(record.payload.len() < test_sequence.len());
 
         std::fs::remove_file(input_path).unwrap();
         std::fs::remove_file(output_path).unwrap();
@@ -138,12 +346,12 @@ mod tests {
         let output_path = "test_rna_unpack_output.txt";
         let test_sequence = b"random_ascii_sequence";
 
-        let mut file = File::create(input_path).unwrap();
-        file.write_all(test_sequence).unwrap();
-
-        let processor = TextProcessor;
         let mode = Mode::RNA;
-        processor.unpack(input_path, output_path, &mode).unwrap();
+        let header = Header::new(&mode, DEFAULT_CODEC, SourceFormat::Text);
+        write_test_archive(input_path, &header, &[Record::new(RecordKind::Sequence, test_sequence.to_vec())]);
+
+        let processor = TextProcessor::default();
+        processor.unpack(input_path, output_path, Some(&mode)).unwrap();
 
         let mut file = File::open(output_path).unwrap();
         let mut content = String::new();
@@ -153,4 +361,110 @@ mod tests {
         std::fs::remove_file(input_path).unwrap();
         std::fs::remove_file(output_path).unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_fastq_processor_round_trip() {
+        let input_path = "test_fastq_processor_input.fastq";
+        let compressed_path = "test_fastq_processor_compressed.fastq";
+        let output_path = "test_fastq_processor_output.fastq";
+        let input = "@read1\nACGTN\n+\nIII#F\n";
+        std::fs::write(input_path, input).unwrap();
+
+        let processor = FastqProcessor::default();
+        processor.compress(input_path, compressed_path, &Mode::DNA).unwrap();
+        processor.unpack(compressed_path, output_path, None).unwrap();
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), input);
+
+        std::fs::remove_file(input_path).unwrap();
+        std::fs::remove_file(compressed_path).unwrap();
+        std::fs::remove_file(output_path).unwrap();
+    }
+
+    #[test]
+    fn test_fasta_processor_line_width() {
+        let input_path = "test_fasta_processor_input.fasta";
+        let compressed_path = "test_fasta_processor_compressed.fasta";
+        let output_path = "test_fasta_processor_output.fasta";
+        std::fs::write(input_path, ">seq\nACGTAC\nGTA\n").unwrap();
+
+        FastaProcessor::default().compress(input_path, compressed_path, &Mode::DNA).unwrap();
+        FastaProcessor::default().with_line_width(Some(4)).unpack(compressed_path, output_path, None).unwrap();
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), ">seq\nACGT\nACGT\nA\n");
+
+        let (fai_path, block_offsets_path) = fasta_index_paths(compressed_path);
+        assert_eq!(std::fs::read_to_string(&fai_path).unwrap(), "seq\t9\t5\t6\t7\n");
+
+        std::fs::remove_file(input_path).unwrap();
+        std::fs::remove_file(compressed_path).unwrap();
+        std::fs::remove_file(output_path).unwrap();
+        std::fs::remove_file(fai_path).unwrap();
+        std::fs::remove_file(block_offsets_path).unwrap();
+    }
+
+    #[test]
+    fn test_verify_against_original_and_checksums() {
+        let input_path = "test_verify_input.fasta";
+        let compressed_path = "test_verify_compressed.fasta";
+        let changed_path = "test_verify_changed.fasta";
+        std::fs::write(input_path, ">seq1\nACGTAC\nGTA\n>seq2\nNNNN\n").unwrap();
+        std::fs::write(changed_path, ">seq1\nACGTAC\nGTA\n>seq2\nNNAN\n").unwrap();
+
+        let processor = FastaProcessor::default();
+        processor.compress(input_path, compressed_path, &Mode::DNA).unwrap();
+        processor.verify(compressed_path, None, None).unwrap();
+        processor.verify(compressed_path, Some(input_path), None).unwrap();
+
+        let err = processor.verify(compressed_path, Some(changed_path), None).unwrap_err();
+        assert!(matches!(err.downcast_ref::<VerifyError>(), Some(VerifyError::Mismatch { offset: 25, line: 5 })));
+        assert!(TextProcessor::default().verify(compressed_path, None, None).is_err());
+
+        let (fai_path, block_offsets_path) = fasta_index_paths(compressed_path);
+        std::fs::remove_file(input_path).unwrap();
+        std::fs::remove_file(compressed_path).unwrap();
+        std::fs::remove_file(changed_path).unwrap();
+        std::fs::remove_file(fai_path).unwrap();
+        std::fs::remove_file(block_offsets_path).unwrap();
+    }
+
+    #[test]
+    fn test_verify_line_endings() {
+        let processors: [(&str, Box<dyn Processor>, &str); 6] = [
+            ("txt", Box::new(TextProcessor::default()), "ACGT\r\nGATTACA\r\n"),
+            ("txt", Box::new(TextProcessor::default()), "ACGT\nGATTACA"),
+            ("fasta", Box::new(FastaProcessor::default()), ">seq1\r\nACGTAC\r\nGTA\r\n"),
+            ("fasta", Box::new(FastaProcessor::default()), ">seq1\nACGTAC\nGTA"),
+            ("fastq", Box::new(FastqProcessor::default()), "@read1\r\nACGTN\r\n+\r\nIIII#\r\n"),
+            ("fastq", Box::new(FastqProcessor::default()), "@read1\nACGTN\n+\nIIII#"),
+        ];
+        for (i, (extension, processor, input)) in processors.iter().enumerate() {
+            let input_path = format!("test_verify_line_endings_input_{}.{}", i, extension);
+            let compressed_path = format!("test_verify_line_endings_compressed_{}.{}", i, extension);
+            std::fs::write(&input_path, input).unwrap();
+
+            processor.compress(&input_path, &compressed_path, &Mode::DNA).unwrap();
+            processor.verify(&compressed_path, Some(&input_path), None).unwrap();
+
+            let (fai_path, block_offsets_path) = fasta_index_paths(&compressed_path);
+            let _ = std::fs::remove_file(fai_path);
+            let _ = std::fs::remove_file(block_offsets_path);
+            std::fs::remove_file(input_path).unwrap();
+            std::fs::remove_file(compressed_path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_verify_lossy_fastq() {
+        let input_path = "test_verify_input.fastq";
+        let compressed_path = "test_verify_compressed.fastq";
+        std::fs::write(input_path, "@read1\nACGTN\n+\nIIII#\n").unwrap();
+
+        let processor = FastqProcessor::default().with_quality_binning(Some(QualityBinning::illumina()));
+        processor.compress(input_path, compressed_path, &Mode::DNA).unwrap();
+        processor.verify(compressed_path, None, None).unwrap();
+        let err = processor.verify(compressed_path, Some(input_path), None).unwrap_err();
+        assert!(matches!(err.downcast_ref::<VerifyError>(), Some(VerifyError::Mismatch { offset: 19, line: 4 })));
+
+        std::fs::remove_file(input_path).unwrap();
+        std::fs::remove_file(compressed_path).unwrap();
+    }
+}
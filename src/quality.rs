@@ -0,0 +1,220 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::errors::CompressionError;
+use crate::varint::{read_varint, write_varint};
+
+const RUN_FLAG: u8 = 0x80;
+const PHRED_OFFSET: u8 = b'!';
+const MAX_PHRED: u8 = b'~' - PHRED_OFFSET;
+
+const ILLUMINA_BINS: [(u8, u8, u8); 7] = [
+    (2, 9, 6),
+    (10, 19, 15),
+    (20, 24, 22),
+    (25, 29, 27),
+    (30, 34, 33),
+    (35, 39, 37),
+    (40, MAX_PHRED, 40),
+];
+
+// Bins are inclusive Phred ranges mapped to a single representative score.
+// Scores outside every bin are kept as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityBinning {
+    bins: Vec<(u8, u8, u8)>,
+}
+
+impl QualityBinning {
+    pub fn illumina() -> Self {
+        Self { bins: ILLUMINA_BINS.to_vec() }
+    }
+
+    pub fn new(mut bins: Vec<(u8, u8, u8)>) -> Result<Self, String> {
+        bins.sort();
+        for &(low, high, value) in &bins {
+            if low > high || high > MAX_PHRED || value > MAX_PHRED {
+                return Err(format!("Invalid quality bin {}-{}:{}", low, high, value));
+            }
+        }
+        if bins.is_empty() || bins.len() > u8::MAX as usize {
+            return Err(format!("Quality binning needs between 1 and {} bins", u8::MAX));
+        }
+        if let Some(pair) = bins.windows(2).find(|pair| pair[0].1 >= pair[1].0) {
+            return Err(format!("Quality bins {}-{} and {}-{} overlap", pair[0].0, pair[0].1, pair[1].0, pair[1].1));
+        }
+        Ok(Self { bins })
+    }
+
+    pub fn bins(&self) -> &[(u8, u8, u8)] {
+        &self.bins
+    }
+
+    pub fn apply(&self, qualities: &mut [u8]) {
+        for score in qualities.iter_mut().filter(|score| is_quality_score(**score)) {
+            let phred = *score - PHRED_OFFSET;
+            if let Some(&(_, _, value)) = self.bins.iter().find(|&&(low, high, _)| (low..=high).contains(&phred)) {
+                *score = value + PHRED_OFFSET;
+            }
+        }
+    }
+}
+
+impl FromStr for QualityBinning {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("illumina") {
+            return Ok(Self::illumina());
+        }
+
+        let invalid = |bin: &str| format!("Invalid quality bin '{}', expected low-high:value", bin);
+        let bins = s
+            .split(',')
+            .map(|bin| {
+                let (range, value) = bin.trim().split_once(':').ok_or_else(|| invalid(bin))?;
+                let (low, high) = range.split_once('-').unwrap_or((range, range));
+                let parse = |field: &str| field.trim().parse::<u8>().map_err(|_| invalid(bin));
+                Ok((parse(low)?, parse(high)?, parse(value)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Self::new(bins)
+    }
+}
+
+impl fmt::Display for QualityBinning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bins: Vec<String> = self.bins.iter().map(|(low, high, value)| format!("{}-{}:{}", low, high, value)).collect();
+        write!(f, "{}", bins.join(","))
+    }
+}
+
+// Phred+33 scores are printable ASCII, so the high bit is free to mark a
+// score that repeats: `score | RUN_FLAG` is followed by the run length minus two.
+pub fn encode_qualities(qualities: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    let mut encoded = Vec::with_capacity(qualities.len() / 2);
+    let mut i = 0;
+    while i < qualities.len() {
+        let score = qualities[i];
+        if !is_quality_score(score) {
+            return Err(CompressionError::UnknownCharacter(score as char));
+        }
+        let run = qualities[i..].iter().take_while(|&&q| q == score).count();
+        if run == 1 {
+            encoded.push(score);
+        } else {
+            encoded.push(score | RUN_FLAG);
+            write_varint(&mut encoded, (run - 2) as u64);
+        }
+        i += run;
+    }
+    Ok(encoded)
+}
+
+// `len` is the length of the record's sequence, which no run may go past.
+pub fn decode_qualities(encoded: &[u8], len: usize) -> Result<Vec<u8>, CompressionError> {
+    let mut input = encoded;
+    let mut qualities = Vec::with_capacity(len);
+    while let Some((&byte, rest)) = input.split_first() {
+        input = rest;
+        let score = byte & !RUN_FLAG;
+        if !is_quality_score(score) {
+            return Err(CompressionError::InvalidEncoding(format!("invalid quality score {}", score)));
+        }
+        let run = match byte & RUN_FLAG {
+            0 => 1,
+            _ => read_varint(&mut input)
+                .ok_or_else(|| CompressionError::InvalidEncoding("truncated quality run".to_string()))?
+                .saturating_add(2) as usize,
+        };
+        if qualities.len().saturating_add(run) > len {
+            return Err(CompressionError::InvalidEncoding("quality scores past the end of the sequence".to_string()));
+        }
+        qualities.resize(qualities.len() + run, score);
+    }
+    if qualities.len() != len {
+        return Err(CompressionError::InvalidEncoding("quality scores do not match the sequence length".to_string()));
+    }
+    Ok(qualities)
+}
+
+fn is_quality_score(score: u8) -> bool {
+    (b'!'..=b'~').contains(&score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let test_qualities: [&[u8]; 4] = [b"", b"I", b"IIIIIIIIII#####FFFF:F:F", b"!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJ~"];
+        for qualities in test_qualities {
+            let encoded = encode_qualities(qualities).unwrap();
+            assert_eq!(decode_qualities(&encoded, qualities.len()).unwrap(), qualities);
+        }
+    }
+
+    #[test]
+    fn test_runs_are_compact() {
+        let qualities = [b"F".repeat(100), b":".repeat(50)].concat();
+        let encoded = encode_qualities(&qualities).unwrap();
+        assert_eq!(encoded.len(), 4);
+    }
+
+    #[test]
+    fn test_never_expands_unique_scores() {
+        let qualities = b"ABCDEFGHIJ";
+        assert_eq!(encode_qualities(qualities).unwrap().len(), qualities.len());
+    }
+
+    #[test]
+    fn test_illumina_binning() {
+        let mut qualities = b"!\"#*+5?@IJ~".to_vec();
+        QualityBinning::illumina().apply(&mut qualities);
+        assert_eq!(qualities, b"!\"''07BBIII");
+    }
+
+    #[test]
+    fn test_parse_binning() {
+        assert_eq!("illumina".parse::<QualityBinning>().unwrap(), QualityBinning::illumina());
+
+        let binning: QualityBinning = "20-93:30, 0-19:10".parse().unwrap();
+        assert_eq!(binning.bins(), &[(0, 19, 10), (20, 93, 30)]);
+        assert_eq!(binning.to_string(), "0-19:10,20-93:30");
+        assert_eq!(binning.to_string().parse::<QualityBinning>().unwrap(), binning);
+
+        let single: QualityBinning = "2:0".parse().unwrap();
+        assert_eq!(single.bins(), &[(2, 2, 0)]);
+
+        for invalid in ["", "10-20", "20-10:15", "0-100:10", "0-10:5,10-20:15", "a-b:c"] {
+            assert!(invalid.parse::<QualityBinning>().is_err(), "{} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn test_binning_shrinks_encoding() {
+        let qualities = b"IJIJJIJIJJIIJ#EFGFGFEFGG".to_vec();
+        let mut binned = qualities.clone();
+        QualityBinning::illumina().apply(&mut binned);
+        assert!(encode_qualities(&binned).unwrap().len() < encode_qualities(&qualities).unwrap().len());
+    }
+
+    #[test]
+    fn test_invalid_scores() {
+        assert!(matches!(encode_qualities(b"II I"), Err(CompressionError::UnknownCharacter(' '))));
+        assert!(matches!(decode_qualities(&[b'I' | RUN_FLAG], 2), Err(CompressionError::InvalidEncoding(_))));
+        assert!(matches!(decode_qualities(&[0x01], 1), Err(CompressionError::InvalidEncoding(_))));
+    }
+
+    #[test]
+    fn test_runs_are_bounded_by_sequence() {
+        let encoded = encode_qualities(b"IIIIII").unwrap();
+        assert_eq!(decode_qualities(&encoded, 6).unwrap(), b"IIIIII");
+        assert!(matches!(decode_qualities(&encoded, 5), Err(CompressionError::InvalidEncoding(_))));
+        assert!(matches!(decode_qualities(&encoded, 7), Err(CompressionError::InvalidEncoding(_))));
+
+        let huge_run = [b'I' | RUN_FLAG, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        assert!(matches!(decode_qualities(&huge_run, 100), Err(CompressionError::InvalidEncoding(_))));
+    }
+}
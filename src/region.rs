@@ -0,0 +1,111 @@
+use std::fmt;
+
+use crate::errors::RegionError;
+use crate::index::SequenceIndex;
+
+// A samtools style region: `name`, `name:start` or `name:start-end`, with
+// 1-based inclusive coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub name: String,
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+}
+
+impl Region {
+    pub fn new(name: &str, start: Option<u64>, end: Option<u64>) -> Self {
+        Self { name: name.to_string(), start, end }
+    }
+
+    // Sequence names may contain `:` themselves, so like samtools the whole
+    // string is looked up in the index first. A `:start-end` suffix is only
+    // split off when it is not a known name and the suffix looks like a range.
+    pub fn parse(s: &str, index: &SequenceIndex) -> Result<Self, RegionError> {
+        let invalid = || RegionError::Invalid(s.to_string());
+        let parse = |position: &str| position.replace(',', "").parse::<u64>().ok().filter(|&position| position > 0);
+
+        let (name, range) = match s.rsplit_once(':') {
+            _ if s.is_empty() => return Err(invalid()),
+            _ if index.find(s).is_some() => return Ok(Self::new(s, None, None)),
+            Some((name, range)) if !name.is_empty() && is_range(range) => (name, range),
+            _ => return Ok(Self::new(s, None, None)),
+        };
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (parse(start).ok_or_else(invalid)?, Some(parse(end).ok_or_else(invalid)?)),
+            None => (parse(range).ok_or_else(invalid)?, None),
+        };
+        if end.is_some_and(|end| end < start) {
+            return Err(invalid());
+        }
+        Ok(Self::new(name, Some(start), end))
+    }
+
+    // Converts to a 0-based half-open range clamped to the sequence length.
+    pub fn bounds(&self, len: u64) -> Result<(u64, u64), RegionError> {
+        let start = self.start.map_or(0, |start| start - 1);
+        let end = self.end.map_or(len, |end| end.min(len));
+        if start > 0 && start >= len {
+            return Err(RegionError::OutOfRange { name: self.name.clone(), start: start + 1, len });
+        }
+        Ok((start, end.max(start)))
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => write!(f, ":{}-{}", start, end),
+            (Some(start), None) => write!(f, ":{}", start),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn is_range(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit() || c == ',' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::IndexEntry;
+
+    #[test]
+    fn test_parse_region() {
+        let index = SequenceIndex::default();
+        let parse = |s: &str| Region::parse(s, &index);
+        assert_eq!(parse("chr1").unwrap(), Region::new("chr1", None, None));
+        assert_eq!(parse("chr1:100").unwrap(), Region::new("chr1", Some(100), None));
+        assert_eq!(parse("chr1:1,000-2,000").unwrap(), Region::new("chr1", Some(1000), Some(2000)));
+        assert_eq!(parse("HLA-A*01:01:1-10").unwrap(), Region::new("HLA-A*01:01", Some(1), Some(10)));
+        assert_eq!(parse("chr1:abc").unwrap(), Region::new("chr1:abc", None, None));
+        assert_eq!(parse(":1-10").unwrap(), Region::new(":1-10", None, None));
+        assert_eq!(parse("chr1:100-200").unwrap().to_string(), "chr1:100-200");
+
+        for invalid in ["", "chr1:0-10", "chr1:20-10", "chr1:1-", "chr1:1-2-3"] {
+            assert!(matches!(parse(invalid), Err(RegionError::Invalid(_))), "{} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn test_names_with_colons() {
+        let entries = ["HLA-A*01:01", "chrUn:12", "chr1"].map(IndexEntry::new).to_vec();
+        let index = SequenceIndex { entries };
+        assert_eq!(Region::parse("HLA-A*01:01", &index).unwrap(), Region::new("HLA-A*01:01", None, None));
+        assert_eq!(Region::parse("HLA-A*01:01:5", &index).unwrap(), Region::new("HLA-A*01:01", Some(5), None));
+        assert_eq!(Region::parse("chrUn:12", &index).unwrap(), Region::new("chrUn:12", None, None));
+        assert_eq!(Region::parse("chrUn:12:3-4", &index).unwrap(), Region::new("chrUn:12", Some(3), Some(4)));
+        assert_eq!(Region::parse("chr1:12", &index).unwrap(), Region::new("chr1", Some(12), None));
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(Region::new("chr1", None, None).bounds(50).unwrap(), (0, 50));
+        assert_eq!(Region::new("chr1", Some(10), Some(20)).bounds(50).unwrap(), (9, 20));
+        assert_eq!(Region::new("chr1", Some(40), Some(100)).bounds(50).unwrap(), (39, 50));
+        assert_eq!(Region::new("chr1", Some(50), None).bounds(50).unwrap(), (49, 50));
+        assert_eq!(Region::new("empty", None, None).bounds(0).unwrap(), (0, 0));
+        assert!(matches!(Region::new("chr1", Some(51), None).bounds(50), Err(RegionError::OutOfRange { .. })));
+    }
+}
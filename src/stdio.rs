@@ -0,0 +1,80 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+// A path of `-` stands for stdin or stdout so archives can be used in pipelines.
+pub const STDIO_PATH: &str = "-";
+
+pub fn is_stdio(path: &str) -> bool {
+    path == STDIO_PATH
+}
+
+// Unbuffered, callers wrap it in a BufReader.
+pub fn open_input(path: &str) -> io::Result<Box<dyn Read>> {
+    match is_stdio(path) {
+        true => Ok(Box::new(io::stdin())),
+        false => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+pub fn create_output(path: &str) -> io::Result<Box<dyn Write>> {
+    match is_stdio(path) {
+        true => Ok(Box::new(BufWriter::new(io::stdout().lock()))),
+        false => Ok(Box::new(BufWriter::new(File::create(path)?))),
+    }
+}
+
+// Reading the index, summary or a region seeks from the end of the archive,
+// which a pipe cannot do.
+pub fn open_seekable(path: &str) -> Result<BufReader<File>, Box<dyn Error>> {
+    if is_stdio(path) {
+        return Err("this operation needs a compressed file, it cannot read from stdin".into());
+    }
+    Ok(BufReader::new(File::open(path)?))
+}
+
+// Counts the bytes read through it, giving the original size of input whose
+// length is not known up front.
+pub struct CountingReader<R: Read> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+
+    #[test]
+    fn test_counting_reader() {
+        let mut reader = BufReader::new(CountingReader::new(">seq1\r\nACGT".as_bytes()));
+        let lines: Vec<String> = reader.by_ref().lines().map(|line| line.unwrap()).collect();
+        assert_eq!(lines, vec![">seq1", "ACGT"]);
+        assert_eq!(reader.get_ref().count(), 11);
+    }
+
+    #[test]
+    fn test_stdio_paths() {
+        assert!(is_stdio("-"));
+        assert!(!is_stdio("-.fasta"));
+        assert!(open_seekable("-").is_err());
+    }
+}
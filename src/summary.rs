@@ -0,0 +1,200 @@
+use std::fmt;
+use std::io::{Read, Seek};
+
+use crate::container::{read_footer, read_record_at, Header, RecordKind};
+use crate::errors::ContainerError;
+use crate::index::SequenceIndex;
+use crate::mode::Mode;
+use crate::varint::{read_varint, write_varint};
+
+// Sequence statistics written next to the footer at compress time, so `info`
+// does not have to decode the archive. Lengths are run-length encoded as
+// (length, count) since reads in a FASTQ file usually share one length. The
+// original size is kept here too, and unpacking is checked against it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArchiveSummary {
+    pub original_len: u64,
+    pub bases: u64,
+    pub n_count: u64,
+    length_runs: Vec<(u64, u64)>,
+}
+
+impl ArchiveSummary {
+    pub fn sequence_count(&self) -> u64 {
+        self.length_runs.iter().map(|&(_, count)| count).sum()
+    }
+
+    pub fn length_runs(&self) -> &[(u64, u64)] {
+        &self.length_runs
+    }
+
+    pub fn load<R: Read + Seek>(reader: &mut R) -> Result<Self, ContainerError> {
+        let offset = read_footer(reader)?.summary_offset.ok_or(ContainerError::MissingSummary)?;
+        let record = read_record_at(reader, offset)?;
+        if record.kind != RecordKind::Summary {
+            return Err(ContainerError::InvalidSummary);
+        }
+        Self::read_from(&record.payload)
+    }
+
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.original_len);
+        write_varint(out, self.bases);
+        write_varint(out, self.n_count);
+        write_varint(out, self.length_runs.len() as u64);
+        for &(len, count) in &self.length_runs {
+            write_varint(out, len);
+            write_varint(out, count);
+        }
+    }
+
+    pub fn read_from(input: &[u8]) -> Result<Self, ContainerError> {
+        let mut input = input;
+        let mut read_value = || read_varint(&mut input).ok_or(ContainerError::InvalidSummary);
+        let original_len = read_value()?;
+        let bases = read_value()?;
+        let n_count = read_value()?;
+        let run_count = read_value()?;
+        let mut length_runs = Vec::new();
+        for _ in 0..run_count {
+            length_runs.push((read_value()?, read_value()?));
+        }
+        Ok(Self { original_len, bases, n_count, length_runs })
+    }
+
+    fn push_length(&mut self, len: u64) {
+        match self.length_runs.last_mut() {
+            Some((run_len, count)) if *run_len == len => *count += 1,
+            _ => self.length_runs.push((len, 1)),
+        }
+    }
+}
+
+// Collects the summary while sequences stream through compression. A FASTA
+// entry spans several lines or blocks, so a sequence stays open until the
+// next one starts.
+#[derive(Debug, Default)]
+pub struct SummaryBuilder {
+    summary: ArchiveSummary,
+    current: Option<u64>,
+}
+
+impl SummaryBuilder {
+    pub fn start_sequence(&mut self) {
+        if let Some(len) = self.current.replace(0) {
+            self.summary.push_length(len);
+        }
+    }
+
+    pub fn extend_sequence(&mut self, sequence: &[u8]) {
+        let len = sequence.len() as u64;
+        self.current = Some(self.current.unwrap_or(0) + len);
+        self.summary.bases += len;
+        self.summary.n_count += sequence.iter().filter(|&&base| base == b'N' || base == b'n').count() as u64;
+    }
+
+    pub fn add_sequence(&mut self, sequence: &[u8]) {
+        self.start_sequence();
+        self.extend_sequence(sequence);
+    }
+
+    pub fn finish(mut self) -> ArchiveSummary {
+        if let Some(len) = self.current.take() {
+            self.summary.push_length(len);
+        }
+        self.summary
+    }
+}
+
+// Everything `info` prints about an archive. Per-record lengths are listed by
+// name when the archive has a sequence index.
+#[derive(Debug)]
+pub struct ArchiveInfo {
+    pub header: Header,
+    pub compressed_len: u64,
+    pub summary: ArchiveSummary,
+    pub index: Option<SequenceIndex>,
+}
+
+impl ArchiveInfo {
+    pub fn ratio(&self) -> f64 {
+        match self.compressed_len {
+            0 => 0.0,
+            len => self.summary.original_len as f64 / len as f64,
+        }
+    }
+}
+
+impl fmt::Display for ArchiveInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Format: {}", self.header.format)?;
+        writeln!(f, "Mode: {:?}", self.header.mode)?;
+        writeln!(f, "Codec: {}", self.header.codec)?;
+        if let Some(binning) = &self.header.quality_binning {
+            writeln!(f, "Quality binning: {} (lossy)", binning)?;
+        }
+        writeln!(f, "Original size: {} bytes", self.summary.original_len)?;
+        writeln!(f, "Compressed size: {} bytes", self.compressed_len)?;
+        writeln!(f, "Ratio: {:.2}", self.ratio())?;
+        writeln!(f, "Records: {}", self.summary.sequence_count())?;
+        writeln!(f, "Bases: {}", self.summary.bases)?;
+        if self.header.mode != Mode::Protein {
+            writeln!(f, "N bases: {}", self.summary.n_count)?;
+        }
+
+        writeln!(f, "Record lengths:")?;
+        match &self.index {
+            Some(index) => {
+                for entry in &index.entries {
+                    writeln!(f, "  {}\t{}", entry.name, entry.len)?;
+                }
+            }
+            None => {
+                for &(len, count) in self.summary.length_runs() {
+                    writeln!(f, "  {} x {}", len, count)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let mut builder = SummaryBuilder::default();
+        builder.add_sequence(b"ACGTN");
+        builder.add_sequence(b"nnACG");
+        builder.start_sequence();
+        builder.extend_sequence(b"ACGT");
+        builder.extend_sequence(b"AC");
+        builder.start_sequence();
+        let summary = builder.finish();
+
+        assert_eq!(summary.length_runs(), &[(5, 2), (6, 1), (0, 1)]);
+        assert_eq!(summary.sequence_count(), 4);
+        assert_eq!(summary.bases, 16);
+        assert_eq!(summary.n_count, 3);
+        assert_eq!(SummaryBuilder::default().finish().sequence_count(), 0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut builder = SummaryBuilder::default();
+        for _ in 0..1000 {
+            builder.add_sequence(&[b'A'; 150]);
+        }
+        builder.add_sequence(b"NNN");
+        let mut summary = builder.finish();
+        summary.original_len = 151_004;
+
+        let mut bytes = Vec::new();
+        summary.write_to(&mut bytes);
+        assert!(bytes.len() < 16);
+        assert_eq!(ArchiveSummary::read_from(&bytes).unwrap(), summary);
+        assert!(matches!(ArchiveSummary::read_from(&bytes[..bytes.len() - 1]), Err(ContainerError::InvalidSummary)));
+    }
+}
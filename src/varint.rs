@@ -0,0 +1,61 @@
+use std::io::{self, Read};
+
+pub fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub fn read_varint(input: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input.split_first()?;
+        *input = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+pub fn read_varint_from<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "varint is too long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_round_trip() {
+        for value in [0, 1, 127, 128, 300, 16_384, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+
+            let mut slice = bytes.as_slice();
+            assert_eq!(read_varint(&mut slice), Some(value));
+            assert!(slice.is_empty());
+
+            assert_eq!(read_varint_from(&mut bytes.as_slice()).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_truncated_varint() {
+        let mut slice: &[u8] = &[0x80, 0x80];
+        assert_eq!(read_varint(&mut slice), None);
+        assert!(read_varint_from(&mut [0x80u8].as_slice()).is_err());
+    }
+}
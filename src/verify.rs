@@ -0,0 +1,111 @@
+use std::io::{self, BufRead, Write};
+
+use crate::errors::VerifyError;
+
+// Compares everything written against an original file instead of storing
+// it, remembering the first differing byte (0-based) and its line (1-based).
+pub struct CompareWriter<R: BufRead> {
+    original: R,
+    written: u64,
+    line: u64,
+    mismatch: Option<(u64, u64)>,
+}
+
+impl<R: BufRead> CompareWriter<R> {
+    pub fn new(original: R) -> Self {
+        Self { original, written: 0, line: 1, mismatch: None }
+    }
+
+    fn compare(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let expected = self.original.fill_buf()?;
+            if expected.is_empty() {
+                self.mismatch = Some((self.written, self.line));
+                return Ok(());
+            }
+            let len = expected.len().min(buf.len());
+            let same = expected[..len].iter().zip(&buf[..len]).take_while(|(a, b)| a == b).count();
+            self.written += same as u64;
+            self.line += buf[..same].iter().filter(|&&byte| byte == b'\n').count() as u64;
+            if same < len {
+                self.mismatch = Some((self.written, self.line));
+                return Ok(());
+            }
+            self.original.consume(len);
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+
+    // Returns the number of matching bytes, or where the contents first differ,
+    // which includes either side ending early.
+    pub fn finish(mut self) -> Result<u64, VerifyError> {
+        if self.mismatch.is_none() && !self.original.fill_buf().map_or(true, |rest| rest.is_empty()) {
+            self.mismatch = Some((self.written, self.line));
+        }
+        match self.mismatch {
+            Some((offset, line)) => Err(VerifyError::Mismatch { offset, line }),
+            None => Ok(self.written),
+        }
+    }
+}
+
+impl<R: BufRead> Write for CompareWriter<R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.mismatch.is_none() {
+            self.compare(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(original: &[u8], written: &[&[u8]]) -> Result<u64, VerifyError> {
+        let mut writer = CompareWriter::new(original);
+        for chunk in written {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish()
+    }
+
+    fn mismatch(result: Result<u64, VerifyError>) -> (u64, u64) {
+        match result {
+            Err(VerifyError::Mismatch { offset, line }) => (offset, line),
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_identical_content() {
+        assert_eq!(compare(b">seq1\nACGT\n", &[b">seq1\n", b"AC", b"GT\n"]).unwrap(), 11);
+        assert_eq!(compare(b"", &[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_line_endings_match() {
+        assert_eq!(compare(b"ACGT\r\nACGT\r\n", &[b"ACGT\r\n", b"ACGT\r\n"]).unwrap(), 12);
+        assert_eq!(compare(b"ACGT\nACGT", &[b"ACGT\n", b"ACGT"]).unwrap(), 9);
+    }
+
+    #[test]
+    fn test_first_mismatch() {
+        assert_eq!(mismatch(compare(b">seq1\nACGT\nTTTT\n", &[b">seq1\nAC", b"GT\nTTAT\n"])), (13, 3));
+        assert_eq!(mismatch(compare(b"ACGT\r\nACGT\r\n", &[b"ACGT\r\nACCT\r\n"])), (8, 2));
+
+        let err = compare(b"ACGT\nACGT\n", &[b"ACGT\nACCT\n"]).unwrap_err();
+        assert_eq!(err.to_string(), "Unpacked content differs from the original at byte offset 7, on line 2");
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        assert_eq!(mismatch(compare(b"ACGT\nAC", &[b"ACGT\n"])), (5, 2));
+        assert_eq!(mismatch(compare(b"ACGT\n", &[b"ACGT\nAC"])), (5, 2));
+    }
+}
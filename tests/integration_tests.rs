@@ -3,6 +3,7 @@ use std::io::BufRead;
 use std::io::Write;
 use std::fs::File;
 
+use compact_sequence::codec::DEFAULT_CODEC;
 use compact_sequence::mode::Mode;
 
 
@@ -19,9 +20,9 @@ fn test_compress_and_unpack() {
         writeln!(file, "{}", test_input).unwrap();
     }
 
-    compact_sequence::compress_to_file(input_file_name, compressed_file_name, &TEST_MODE).unwrap();
+    compact_sequence::compress_to_file(input_file_name, compressed_file_name, &TEST_MODE, DEFAULT_CODEC).unwrap();
     
-    compact_sequence::unpack_from_file(compressed_file_name, unpacked_file_name, &TEST_MODE).unwrap();
+    compact_sequence::unpack_from_file(compressed_file_name, unpacked_file_name, Some(&TEST_MODE)).unwrap();
 
     let unpacked_file = File::open(unpacked_file_name).unwrap();
     let reader = BufReader::new(unpacked_file);